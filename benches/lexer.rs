@@ -0,0 +1,38 @@
+// Compares lexing with borrowed tokens against converting every token into
+// an owned `Token`. Run with `cargo bench`.
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use lexer_and_parser::Lexer;
+
+const SOURCE: &str = include_str!("../test_code/tokenize.c");
+const TARGET_SIZE: usize = 8 * 1024 * 1024;
+const RUNS: u32 = 5;
+
+fn bench(name: &str, input: &str, mut f: impl FnMut(&str) -> usize) {
+    let mut best = Duration::MAX;
+    let mut tokens = 0;
+    for _ in 0..RUNS {
+        let start = Instant::now();
+        tokens = black_box(f(black_box(input)));
+        best = best.min(start.elapsed());
+    }
+
+    let mb = input.len() as f64 / (1024.0 * 1024.0);
+    println!(
+        "{:<10} {:>9} tokens  {:>8.2?}  {:>7.1} MiB/s",
+        name,
+        tokens,
+        best,
+        mb / best.as_secs_f64(),
+    );
+}
+
+fn main() {
+    let input = SOURCE.repeat(TARGET_SIZE / SOURCE.len() + 1);
+    println!("lexing {:.1} MiB of C, best of {} runs", input.len() as f64 / (1024.0 * 1024.0), RUNS);
+
+    bench("borrowed", &input, |input| Lexer::new(input).count());
+    bench("owned", &input, |input| Lexer::new(input).tokenize().len());
+}
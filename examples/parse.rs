@@ -1,4 +1,7 @@
-use lexer_and_parser::{Lexer, Parser};
+use std::io::IsTerminal;
+
+use lexer_and_parser::diagnostics::{Diagnostic, Renderer};
+use lexer_and_parser::{Lexer, ParseError, Parser};
 
 fn main() {
     let correct = std::fs::read_to_string("test_code/parse_correct.c").expect("Failed to read source file");
@@ -8,16 +11,16 @@ fn main() {
 
     for token_line in Lexer::new(&correct).parse() {
         for token in token_line.tokens.iter() {
-            println!("{:02}:{:02} {:?}", token.row, token.column, token.token);
+            println!("{:02}:{:02} {:?}", token.span.start.row, token.span.start.column, token.token);
         }
     }
 
     let mut parser = Parser::new(Lexer::new(&correct));
 
-    if let Some(err) = parser.parse_program().err() {
-        eprintln!("{}", err);
-    } else {
-        println!("Parsed successfully");
+    let (program, errors) = parser.parse_program();
+    report("test_code/parse_correct.c", &correct, &errors);
+    if errors.is_empty() {
+        println!("Parsed successfully\n{:#?}", program);
     }
 
     println!("\n=====Parsing incorrect source code =====");
@@ -25,9 +28,16 @@ fn main() {
     let lexer = Lexer::new(&incorrect);
     let mut parser = Parser::new(lexer);
 
-    if let Some(err) = parser.parse_program().err() {
-        eprintln!("{}", err);
-    } else {
+    let (_, errors) = parser.parse_program();
+    report("test_code/parse_incorrect.c", &incorrect, &errors);
+    if errors.is_empty() {
         println!("Parsed successfully");
     }
 }
+
+fn report(file_name: &str, source: &str, errors: &[ParseError]) {
+    let renderer = Renderer::new(file_name, source).with_color(std::io::stderr().is_terminal());
+    for err in errors {
+        eprintln!("{}", renderer.render(&Diagnostic::from_parse_error(err)));
+    }
+}
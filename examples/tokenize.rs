@@ -1,7 +1,11 @@
+use std::io::IsTerminal;
+
+use lexer_and_parser::diagnostics::{Diagnostic, Renderer};
 use lexer_and_parser::{Lexer, Token};
 
 fn main() {
-    let source_code = std::fs::read_to_string("test_code/tokenize_error.c").expect("Failed to read source file");
+    let file_name = "test_code/tokenize_error.c";
+    let source_code = std::fs::read_to_string(file_name).expect("Failed to read source file");
     
     let token_lines = Lexer::new(&source_code).parse();
     // for line in token_lines.iter().filter(|line| {
@@ -22,4 +26,11 @@ fn main() {
                 println!("{:?} ", token);
             });
     }
+
+    let renderer = Renderer::new(file_name, &source_code).with_color(std::io::stderr().is_terminal());
+    for token in token_lines.iter().flat_map(|line| line.tokens.iter()) {
+        if let Some(diagnostic) = Diagnostic::from_lex_error(token) {
+            eprintln!("{}", renderer.render(&diagnostic));
+        }
+    }
 }
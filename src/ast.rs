@@ -0,0 +1,709 @@
+use crate::number::{self, Constant, NumberError};
+use crate::token::{Keyword, NumberBase, NumberKind, NumberSuffix, Position};
+
+// program -> items
+// items -> item items | ε
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Program {
+    pub items: Vec<Item>,
+    pub pos: Position,
+}
+
+// item -> function-definition | declaration | block
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Item {
+    Function(FunctionDef),
+    Decl(Declaration),
+    // The whole program of the teaching grammar
+    Block(Block),
+    // Placeholder for an item that failed to parse
+    Error {
+        pos: Position,
+    },
+}
+
+impl Item {
+    pub fn pos(&self) -> Position {
+        match self {
+            Item::Function(function) => function.pos,
+            Item::Decl(decl) => decl.pos,
+            Item::Block(block) => block.pos,
+            Item::Error { pos } => *pos,
+        }
+    }
+
+    // Move every position in the item, for reuse after an edit
+    pub(crate) fn map_positions(&mut self, f: &impl Fn(Position) -> Position) {
+        match self {
+            Item::Function(function) => {
+                function.specifiers.map_positions(f);
+                function.declarator.map_positions(f);
+                function.body.map_positions(f);
+                function.pos = f(function.pos);
+            }
+            Item::Decl(decl) => decl.map_positions(f),
+            Item::Block(block) => block.map_positions(f),
+            Item::Error { pos } => *pos = f(*pos),
+        }
+    }
+}
+
+// function-definition -> specifiers declarator block, where the declarator
+// declares a function: `derived` starts with `Derived::Function`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FunctionDef {
+    pub specifiers: DeclSpecifiers,
+    pub declarator: Declarator,
+    pub body: Block,
+    pub pos: Position,
+}
+
+// block -> { stmts }
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Block {
+    pub stmts: Vec<Stmt>,
+    pub pos: Position,
+}
+
+impl Block {
+    pub(crate) fn map_positions(&mut self, f: &impl Fn(Position) -> Position) {
+        for stmt in &mut self.stmts {
+            stmt.map_positions(f);
+        }
+        self.pos = f(self.pos);
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Stmt {
+    // expr ; | ;
+    Expr {
+        expr: Option<Expr>,
+        pos: Position,
+    },
+    // if (expr) stmt restIf
+    If {
+        cond: Expr,
+        then_branch: Box<Stmt>,
+        else_branch: Option<Box<Stmt>>,
+        pos: Position,
+    },
+    // while (expr) stmt
+    While {
+        cond: Expr,
+        body: Box<Stmt>,
+        pos: Position,
+    },
+    // do stmt while (expr) ;, where the teaching grammar leaves out the `;`
+    DoWhile {
+        body: Box<Stmt>,
+        cond: Expr,
+        pos: Position,
+    },
+    // for ( init cond ; step ) stmt, with every part optional; a
+    // declaration in `init` is only in scope in the loop
+    For {
+        init: Option<Box<ForInit>>,
+        cond: Option<Expr>,
+        step: Option<Expr>,
+        body: Box<Stmt>,
+        pos: Position,
+    },
+    // switch (expr) stmt, with the cases labeling statements of the body
+    Switch {
+        cond: Expr,
+        body: Box<Stmt>,
+        pos: Position,
+    },
+    // case expr : stmt; `case 1: case 2: stmt` nests
+    Case {
+        value: Expr,
+        body: Box<Stmt>,
+        pos: Position,
+    },
+    // default : stmt
+    Default {
+        body: Box<Stmt>,
+        pos: Position,
+    },
+    // id : stmt
+    Labeled {
+        label: Ident,
+        body: Box<Stmt>,
+        pos: Position,
+    },
+    // goto id ;
+    Goto {
+        label: Ident,
+        pos: Position,
+    },
+    // continue ;
+    Continue {
+        pos: Position,
+    },
+    // break ;, where the teaching grammar leaves out the `;`
+    Break {
+        pos: Position,
+    },
+    // return expr ; | return ;
+    Return {
+        value: Option<Expr>,
+        pos: Position,
+    },
+    // block
+    Block(Block),
+    // declaration
+    Decl(Declaration),
+    // Placeholder for a statement that failed to parse
+    Error {
+        pos: Position,
+    },
+}
+
+impl Stmt {
+    pub fn pos(&self) -> Position {
+        match self {
+            Stmt::Expr { pos, .. }
+            | Stmt::If { pos, .. }
+            | Stmt::While { pos, .. }
+            | Stmt::DoWhile { pos, .. }
+            | Stmt::For { pos, .. }
+            | Stmt::Switch { pos, .. }
+            | Stmt::Case { pos, .. }
+            | Stmt::Default { pos, .. }
+            | Stmt::Labeled { pos, .. }
+            | Stmt::Goto { pos, .. }
+            | Stmt::Continue { pos }
+            | Stmt::Break { pos }
+            | Stmt::Return { pos, .. }
+            | Stmt::Error { pos } => *pos,
+            Stmt::Block(block) => block.pos,
+            Stmt::Decl(decl) => decl.pos,
+        }
+    }
+
+    // Move every position in the statement, for reuse after an edit
+    pub(crate) fn map_positions(&mut self, f: &impl Fn(Position) -> Position) {
+        match self {
+            Stmt::Expr { expr, pos } => {
+                if let Some(expr) = expr {
+                    expr.map_positions(f);
+                }
+                *pos = f(*pos);
+            }
+            Stmt::If { cond, then_branch, else_branch, pos } => {
+                cond.map_positions(f);
+                then_branch.map_positions(f);
+                if let Some(else_branch) = else_branch {
+                    else_branch.map_positions(f);
+                }
+                *pos = f(*pos);
+            }
+            Stmt::While { cond, body, pos }
+            | Stmt::DoWhile { body, cond, pos }
+            | Stmt::Switch { cond, body, pos }
+            | Stmt::Case { value: cond, body, pos } => {
+                cond.map_positions(f);
+                body.map_positions(f);
+                *pos = f(*pos);
+            }
+            Stmt::For { init, cond, step, body, pos } => {
+                match init.as_deref_mut() {
+                    Some(ForInit::Decl(decl)) => decl.map_positions(f),
+                    Some(ForInit::Expr(expr)) => expr.map_positions(f),
+                    None => {}
+                }
+                for expr in [cond, step].into_iter().flatten() {
+                    expr.map_positions(f);
+                }
+                body.map_positions(f);
+                *pos = f(*pos);
+            }
+            Stmt::Default { body, pos } => {
+                body.map_positions(f);
+                *pos = f(*pos);
+            }
+            Stmt::Labeled { label, body, pos } => {
+                label.pos = f(label.pos);
+                body.map_positions(f);
+                *pos = f(*pos);
+            }
+            Stmt::Goto { label, pos } => {
+                label.pos = f(label.pos);
+                *pos = f(*pos);
+            }
+            Stmt::Return { value, pos } => {
+                if let Some(value) = value {
+                    value.map_positions(f);
+                }
+                *pos = f(*pos);
+            }
+            Stmt::Continue { pos } | Stmt::Break { pos } | Stmt::Error { pos } => *pos = f(*pos),
+            Stmt::Block(block) => block.map_positions(f),
+            Stmt::Decl(decl) => decl.map_positions(f),
+        }
+    }
+}
+
+// The first part of a for loop: declaration | expr ;
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ForInit {
+    Decl(Declaration),
+    Expr(Expr),
+}
+
+// expr -> expr op expr | op expr | expr op | primary, by the operator table
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Expr {
+    // expr op expr, for the operators that always evaluate both sides
+    Binary {
+        op: BinOp,
+        lhs: Box<Expr>,
+        rhs: Box<Expr>,
+        pos: Position,
+    },
+    // expr && expr | expr || expr, short-circuiting: `rhs` is only
+    // evaluated when `lhs` does not decide the result
+    Logical {
+        op: LogicalOp,
+        lhs: Box<Expr>,
+        rhs: Box<Expr>,
+        pos: Position,
+    },
+    // op expr | expr ++ | expr --
+    Unary {
+        op: UnaryOp,
+        operand: Box<Expr>,
+        pos: Position,
+    },
+    // expr = expr | expr op= expr, with `op` None for plain `=`
+    Assign {
+        op: Option<BinOp>,
+        target: Box<Expr>,
+        value: Box<Expr>,
+        pos: Position,
+    },
+    // expr ? expr : expr
+    Conditional {
+        cond: Box<Expr>,
+        then_branch: Box<Expr>,
+        else_branch: Box<Expr>,
+        pos: Position,
+    },
+    // expr , expr
+    Comma {
+        lhs: Box<Expr>,
+        rhs: Box<Expr>,
+        pos: Position,
+    },
+    // ( type-name ) expr
+    Cast {
+        ty: Box<TypeName>,
+        expr: Box<Expr>,
+        pos: Position,
+    },
+    // sizeof expr
+    SizeofExpr {
+        expr: Box<Expr>,
+        pos: Position,
+    },
+    // sizeof ( type-name )
+    SizeofType {
+        ty: Box<TypeName>,
+        pos: Position,
+    },
+    // expr [ expr ]
+    Index {
+        base: Box<Expr>,
+        index: Box<Expr>,
+        pos: Position,
+    },
+    // expr ( args ), with the arguments parsed like assignment right sides
+    Call {
+        callee: Box<Expr>,
+        args: Vec<Expr>,
+        pos: Position,
+    },
+    // expr . id | expr -> id
+    Member {
+        base: Box<Expr>,
+        member: Ident,
+        arrow: bool,
+        pos: Position,
+    },
+    // id
+    Ident(Ident),
+    // num
+    Num(Number),
+    // 'c'
+    Char {
+        value: u32,
+        pos: Position,
+    },
+    // "string" "literals", concatenated
+    Str {
+        value: Vec<u8>,
+        pos: Position,
+    },
+    // ( expr )
+    Paren {
+        expr: Box<Expr>,
+        pos: Position,
+    },
+    // Placeholder for an expression that failed to parse
+    Error {
+        pos: Position,
+    },
+}
+
+impl Expr {
+    pub fn pos(&self) -> Position {
+        match self {
+            Expr::Binary { pos, .. }
+            | Expr::Logical { pos, .. }
+            | Expr::Unary { pos, .. }
+            | Expr::Assign { pos, .. }
+            | Expr::Conditional { pos, .. }
+            | Expr::Comma { pos, .. }
+            | Expr::Cast { pos, .. }
+            | Expr::SizeofExpr { pos, .. }
+            | Expr::SizeofType { pos, .. }
+            | Expr::Index { pos, .. }
+            | Expr::Call { pos, .. }
+            | Expr::Member { pos, .. }
+            | Expr::Char { pos, .. }
+            | Expr::Str { pos, .. }
+            | Expr::Paren { pos, .. }
+            | Expr::Error { pos } => *pos,
+            Expr::Ident(ident) => ident.pos,
+            Expr::Num(num) => num.pos,
+        }
+    }
+
+    pub(crate) fn map_positions(&mut self, f: &impl Fn(Position) -> Position) {
+        match self {
+            Expr::Binary { lhs, rhs, pos, .. }
+            | Expr::Logical { lhs, rhs, pos, .. }
+            | Expr::Assign { target: lhs, value: rhs, pos, .. }
+            | Expr::Comma { lhs, rhs, pos }
+            | Expr::Index { base: lhs, index: rhs, pos } => {
+                lhs.map_positions(f);
+                rhs.map_positions(f);
+                *pos = f(*pos);
+            }
+            Expr::Conditional { cond, then_branch, else_branch, pos } => {
+                cond.map_positions(f);
+                then_branch.map_positions(f);
+                else_branch.map_positions(f);
+                *pos = f(*pos);
+            }
+            Expr::Cast { ty, expr, pos } => {
+                ty.map_positions(f);
+                expr.map_positions(f);
+                *pos = f(*pos);
+            }
+            Expr::SizeofType { ty, pos } => {
+                ty.map_positions(f);
+                *pos = f(*pos);
+            }
+            Expr::Call { callee, args, pos } => {
+                callee.map_positions(f);
+                for arg in args {
+                    arg.map_positions(f);
+                }
+                *pos = f(*pos);
+            }
+            Expr::Member { base, member, pos, .. } => {
+                base.map_positions(f);
+                member.pos = f(member.pos);
+                *pos = f(*pos);
+            }
+            Expr::Unary { operand: expr, pos, .. } | Expr::SizeofExpr { expr, pos } | Expr::Paren { expr, pos } => {
+                expr.map_positions(f);
+                *pos = f(*pos);
+            }
+            Expr::Ident(Ident { pos, .. })
+            | Expr::Num(Number { pos, .. })
+            | Expr::Char { pos, .. }
+            | Expr::Str { pos, .. }
+            | Expr::Error { pos } => *pos = f(*pos),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinOp {
+    Mul,    // *
+    Div,    // /
+    Rem,    // %
+    Add,    // +
+    Sub,    // -
+    Shl,    // <<
+    Shr,    // >>
+    Lt,     // <
+    Gt,     // >
+    Le,     // <=
+    Ge,     // >=
+    Eq,     // ==
+    Ne,     // !=
+    BitAnd, // &
+    BitXor, // ^
+    BitOr,  // |
+    // An operator added to the `OperatorTable`, by spelling
+    Extension(&'static str),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogicalOp {
+    And, // &&
+    Or,  // ||
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnaryOp {
+    PreInc,  // ++ expr
+    PreDec,  // -- expr
+    AddrOf,  // &
+    Deref,   // *
+    Plus,    // +
+    Neg,     // -
+    BitNot,  // ~
+    Not,     // !
+    PostInc, // expr ++
+    PostDec, // expr --
+    // An operator added to the `OperatorTable`, by spelling
+    Extension(&'static str),
+}
+
+// type-name -> specifiers abstract-declarator, e.g. `unsigned long *`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TypeName {
+    pub specifiers: DeclSpecifiers,
+    pub declarator: Declarator,
+    pub pos: Position,
+}
+
+impl TypeName {
+    pub(crate) fn map_positions(&mut self, f: &impl Fn(Position) -> Position) {
+        self.specifiers.map_positions(f);
+        self.declarator.map_positions(f);
+        self.pos = f(self.pos);
+    }
+}
+
+// declaration -> specifiers init-declarators ;
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Declaration {
+    pub specifiers: DeclSpecifiers,
+    pub declarators: Vec<InitDeclarator>,
+    pub pos: Position,
+}
+
+impl Declaration {
+    pub(crate) fn map_positions(&mut self, f: &impl Fn(Position) -> Position) {
+        self.specifiers.map_positions(f);
+        for init_declarator in &mut self.declarators {
+            init_declarator.declarator.map_positions(f);
+            if let Some(init) = &mut init_declarator.init {
+                init.map_positions(f);
+            }
+            if let Some(width) = &mut init_declarator.width {
+                width.map_positions(f);
+            }
+        }
+        self.pos = f(self.pos);
+    }
+}
+
+// Everything before the declarators, in source order within each kind
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeclSpecifiers {
+    // typedef extern static _Thread_local auto register
+    pub storage: Vec<Keyword>,
+    // const volatile restrict _Atomic
+    pub qualifiers: Vec<Keyword>,
+    // inline _Noreturn
+    pub function: Vec<Keyword>,
+    pub ty: TypeSpecifier,
+    pub pos: Position,
+}
+
+impl DeclSpecifiers {
+    pub fn is_typedef(&self) -> bool {
+        self.storage.contains(&Keyword::Typedef)
+    }
+
+    pub(crate) fn map_positions(&mut self, f: &impl Fn(Position) -> Position) {
+        match &mut self.ty {
+            TypeSpecifier::Builtin(_) => {}
+            TypeSpecifier::Struct(spec) => {
+                if let Some(tag) = &mut spec.tag {
+                    tag.pos = f(tag.pos);
+                }
+                for field in spec.fields.iter_mut().flatten() {
+                    field.map_positions(f);
+                }
+                spec.pos = f(spec.pos);
+            }
+            TypeSpecifier::Enum(spec) => {
+                if let Some(tag) = &mut spec.tag {
+                    tag.pos = f(tag.pos);
+                }
+                for enumerator in spec.enumerators.iter_mut().flatten() {
+                    enumerator.name.pos = f(enumerator.name.pos);
+                    if let Some(value) = &mut enumerator.value {
+                        value.map_positions(f);
+                    }
+                }
+                spec.pos = f(spec.pos);
+            }
+            TypeSpecifier::Typedef(name) => name.pos = f(name.pos),
+        }
+        self.pos = f(self.pos);
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TypeSpecifier {
+    // void char short int long float double signed unsigned _Bool _Complex,
+    // as many as were written
+    Builtin(Vec<Keyword>),
+    Struct(StructSpecifier),
+    Enum(EnumSpecifier),
+    // A name declared with typedef
+    Typedef(Ident),
+}
+
+// struct id { declarations } | struct id | struct { declarations }, and
+// the same with union
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StructSpecifier {
+    pub union: bool,
+    pub tag: Option<Ident>,
+    // None when the struct is only referred to, not defined
+    pub fields: Option<Vec<Declaration>>,
+    pub pos: Position,
+}
+
+// enum id { enumerators } | enum id | enum { enumerators }
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EnumSpecifier {
+    pub tag: Option<Ident>,
+    pub enumerators: Option<Vec<Enumerator>>,
+    pub pos: Position,
+}
+
+// id | id = expr
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Enumerator {
+    pub name: Ident,
+    pub value: Option<Expr>,
+}
+
+// declarator | declarator = initializer, or in a struct or union
+// declarator | declarator : expr | : expr
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InitDeclarator {
+    pub declarator: Declarator,
+    pub init: Option<Initializer>,
+    // The width of a bit-field, whose declarator may then have no name
+    pub width: Option<Expr>,
+}
+
+// A declared name and how its type derives from the specifiers' type,
+// read from the name outward: `int (*fp[3])(char)` makes `fp` an
+// `[Array(3), Pointer, Function(char)]` of int. Abstract declarators, as in
+// type names and unnamed parameters, have no name.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Declarator {
+    pub name: Option<Ident>,
+    pub derived: Vec<Derived>,
+    pub pos: Position,
+}
+
+impl Declarator {
+    pub(crate) fn map_positions(&mut self, f: &impl Fn(Position) -> Position) {
+        if let Some(name) = &mut self.name {
+            name.pos = f(name.pos);
+        }
+        for derived in &mut self.derived {
+            match derived {
+                Derived::Pointer { .. } => {}
+                Derived::Array { len } => {
+                    if let Some(len) = len {
+                        len.map_positions(f);
+                    }
+                }
+                Derived::Function { params, .. } => {
+                    for param in params {
+                        param.specifiers.map_positions(f);
+                        param.declarator.map_positions(f);
+                    }
+                }
+            }
+        }
+        self.pos = f(self.pos);
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Derived {
+    // * qualifiers
+    Pointer { qualifiers: Vec<Keyword> },
+    // [ expr ] | [ ]
+    Array { len: Option<Box<Expr>> },
+    // ( params ) | ( params , ... ) | ( )
+    Function { params: Vec<Param>, variadic: bool },
+}
+
+// specifiers declarator, where the declarator may be abstract
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Param {
+    pub specifiers: DeclSpecifiers,
+    pub declarator: Declarator,
+}
+
+// initializer -> expr | { initializers } | { initializers , }
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Initializer {
+    Expr(Expr),
+    List {
+        items: Vec<Initializer>,
+        pos: Position,
+    },
+}
+
+impl Initializer {
+    pub(crate) fn map_positions(&mut self, f: &impl Fn(Position) -> Position) {
+        match self {
+            Initializer::Expr(expr) => expr.map_positions(f),
+            Initializer::List { items, pos } => {
+                for item in items {
+                    item.map_positions(f);
+                }
+                *pos = f(*pos);
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Ident {
+    pub name: String,
+    pub pos: Position,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Number {
+    pub literal: String,
+    pub kind: NumberKind,
+    pub base: NumberBase,
+    pub suffix: Option<NumberSuffix>,
+    pub pos: Position,
+}
+
+impl Number {
+    pub fn value(&self) -> Result<Constant, NumberError> {
+        number::evaluate(&self.literal, self.kind, self.base, self.suffix)
+    }
+}
@@ -0,0 +1,307 @@
+use crate::token::{Keyword, Punct, DIGRAPHS};
+use crate::trie::Trie;
+
+/// The revision of the C standard the lexer follows. Newer revisions enable
+/// more literal forms, e.g. hexadecimal floats in C99 and binary constants
+/// and digit separators in C23.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub enum Standard {
+    C89,
+    C99,
+    C11,
+    #[default]
+    C17,
+    C23,
+}
+
+/// A preset keyword and punctuator set.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LanguageProfile {
+    /// The subset used by the grammar in the README: just enough keywords
+    /// and operators for blocks, assignments, conditions and loops.
+    Teaching,
+    C89,
+    C99,
+    C11,
+    C17,
+    C23,
+}
+
+impl LanguageProfile {
+    /// The standard whose literal forms the profile accepts.
+    pub fn standard(&self) -> Standard {
+        match self {
+            LanguageProfile::C89 => Standard::C89,
+            LanguageProfile::C99 => Standard::C99,
+            LanguageProfile::C11 => Standard::C11,
+            LanguageProfile::Teaching | LanguageProfile::C17 => Standard::C17,
+            LanguageProfile::C23 => Standard::C23,
+        }
+    }
+
+    pub fn keywords(&self) -> Vec<Keyword> {
+        let additions: &[&[Keyword]] = match self {
+            LanguageProfile::Teaching => return TEACHING_KEYWORDS.to_vec(),
+            LanguageProfile::C89 => &[C89_KEYWORDS],
+            LanguageProfile::C99 => &[C89_KEYWORDS, C99_KEYWORDS],
+            // C17 only fixed defects, its keywords are C11's
+            LanguageProfile::C11 | LanguageProfile::C17 => &[C89_KEYWORDS, C99_KEYWORDS, C11_KEYWORDS],
+            LanguageProfile::C23 => &[C89_KEYWORDS, C99_KEYWORDS, C11_KEYWORDS, C23_KEYWORDS],
+        };
+        additions.concat()
+    }
+
+    /// Whether the profile has digraphs, which came with C95.
+    pub fn digraphs(&self) -> bool {
+        !matches!(self, LanguageProfile::Teaching | LanguageProfile::C89)
+    }
+
+    pub fn puncts(&self) -> Vec<Punct> {
+        match self {
+            LanguageProfile::Teaching => TEACHING_PUNCTS.to_vec(),
+            // Every revision has the same punctuators, digraphs aside
+            _ => Punct::ALL.to_vec(),
+        }
+    }
+}
+
+// Keywords of the README grammar
+const TEACHING_KEYWORDS: &[Keyword] = &[Keyword::If, Keyword::Else, Keyword::While, Keyword::Do, Keyword::Break];
+
+// Operators of the teaching grammar: arithmetic, comparisons and logic
+const TEACHING_PUNCTS: &[Punct] = &[
+    Punct::LBrace,
+    Punct::RBrace,
+    Punct::LParen,
+    Punct::RParen,
+    Punct::LBracket,
+    Punct::RBracket,
+    Punct::Semi,
+    Punct::Eq,
+    Punct::Plus,
+    Punct::Minus,
+    Punct::Star,
+    Punct::Slash,
+    Punct::Lt,
+    Punct::Le,
+    Punct::Gt,
+    Punct::Ge,
+    Punct::EqEq,
+    Punct::Ne,
+    Punct::AmpAmp,
+    Punct::PipePipe,
+    Punct::Bang,
+];
+
+// C89 3.1.1
+const C89_KEYWORDS: &[Keyword] = &[
+    Keyword::Auto,
+    Keyword::Break,
+    Keyword::Case,
+    Keyword::Char,
+    Keyword::Const,
+    Keyword::Continue,
+    Keyword::Default,
+    Keyword::Do,
+    Keyword::Double,
+    Keyword::Else,
+    Keyword::Enum,
+    Keyword::Extern,
+    Keyword::Float,
+    Keyword::For,
+    Keyword::Goto,
+    Keyword::If,
+    Keyword::Int,
+    Keyword::Long,
+    Keyword::Register,
+    Keyword::Return,
+    Keyword::Short,
+    Keyword::Signed,
+    Keyword::Sizeof,
+    Keyword::Static,
+    Keyword::Struct,
+    Keyword::Switch,
+    Keyword::Typedef,
+    Keyword::Union,
+    Keyword::Unsigned,
+    Keyword::Void,
+    Keyword::Volatile,
+    Keyword::While,
+];
+
+const C99_KEYWORDS: &[Keyword] = &[
+    Keyword::Inline,
+    Keyword::Restrict,
+    Keyword::_Bool,
+    Keyword::_Complex,
+    Keyword::_Imaginary,
+];
+
+const C11_KEYWORDS: &[Keyword] = &[
+    Keyword::_Alignas,
+    Keyword::_Alignof,
+    Keyword::_Atomic,
+    Keyword::_Generic,
+    Keyword::_Noreturn,
+    Keyword::_StaticAssert,
+    Keyword::_ThreadLocal,
+];
+
+const C23_KEYWORDS: &[Keyword] = &[
+    Keyword::Alignas,
+    Keyword::Alignof,
+    Keyword::Bool,
+    Keyword::Constexpr,
+    Keyword::False,
+    Keyword::Nullptr,
+    Keyword::StaticAssert,
+    Keyword::ThreadLocal,
+    Keyword::True,
+    Keyword::Typeof,
+    Keyword::TypeofUnqual,
+    Keyword::_BitInt,
+    Keyword::_Decimal32,
+    Keyword::_Decimal64,
+    Keyword::_Decimal128,
+];
+
+/// What the lexer recognises: the literal forms of a C standard plus the
+/// reserved keywords and punctuators. Start from a `LanguageProfile` and add
+/// spellings of your own for DSL variants:
+///
+/// ```
+/// use lexer_and_parser::{LanguageProfile, Lexer, LexerConfig, Token};
+///
+/// let config = LexerConfig::new(LanguageProfile::Teaching)
+///     .with_keyword("print")
+///     .with_punct(":=");
+/// let tokens: Vec<_> = Lexer::new_with_config("print x := 1", &config).map(|t| t.token).collect();
+/// assert!(matches!(tokens[0], Token::Keyword(k) if k.as_str() == "print"));
+/// assert!(matches!(tokens[2], Token::Symbol(p) if p.as_str() == ":="));
+/// ```
+#[derive(Debug, Clone)]
+pub struct LexerConfig {
+    standard: Standard,
+    keywords: Vec<Keyword>,
+    // Indexed like `Keyword::ALL`, for a quick lookup
+    reserved: Vec<bool>,
+    extensions: Vec<&'static str>,
+    puncts: Vec<Punct>,
+    digraphs: bool,
+    trigraphs: bool,
+    trie: Trie<Punct>,
+}
+
+impl LexerConfig {
+    pub fn new(profile: LanguageProfile) -> Self {
+        let puncts = profile.puncts();
+        let mut config = LexerConfig {
+            standard: profile.standard(),
+            keywords: Vec::new(),
+            reserved: vec![false; Keyword::ALL.len()],
+            extensions: Vec::new(),
+            trie: build_trie(&puncts, profile.digraphs()),
+            puncts,
+            digraphs: profile.digraphs(),
+            trigraphs: false,
+        };
+        for keyword in profile.keywords() {
+            config.reserve(keyword);
+        }
+        config
+    }
+
+    /// Follow the literal forms of `standard` whatever the profile.
+    pub fn with_standard(mut self, standard: Standard) -> Self {
+        self.standard = standard;
+        self
+    }
+
+    /// Reserve `spelling` as a keyword. It has to look like an identifier;
+    /// the spelling of a built-in keyword reserves that keyword. Any other
+    /// spelling lives on in `Keyword::Extension`, hence `'static`.
+    pub fn with_keyword(mut self, spelling: &'static str) -> Self {
+        self.reserve(Keyword::from_spelling(spelling).unwrap_or(Keyword::Extension(spelling)));
+        self
+    }
+
+    fn reserve(&mut self, keyword: Keyword) {
+        if !self.keywords.contains(&keyword) {
+            self.keywords.push(keyword);
+            match keyword {
+                Keyword::Extension(spelling) => self.extensions.push(spelling),
+                _ => self.reserved[keyword.index().expect("built-in keyword")] = true,
+            }
+        }
+    }
+
+    /// Recognise `spelling` as a punctuator, with maximal munch against the
+    /// others. Like `with_keyword`, a new spelling lives on in
+    /// `Punct::Extension`.
+    pub fn with_punct(mut self, spelling: &'static str) -> Self {
+        let punct = Punct::from_spelling(spelling).unwrap_or(Punct::Extension(spelling));
+        if !self.puncts.contains(&punct) {
+            self.puncts.push(punct);
+            self.trie = build_trie(&self.puncts, self.digraphs);
+        }
+        self
+    }
+
+    /// Lex `<:` `:>` `<%` `%>` `%:` `%:%:` as the punctuators they stand for.
+    pub fn with_digraphs(mut self, digraphs: bool) -> Self {
+        self.digraphs = digraphs;
+        self.trie = build_trie(&self.puncts, digraphs);
+        self
+    }
+
+    /// Replace the `??=`-style trigraphs before lexing. Off by default, as
+    /// in most compilers; C23 dropped them.
+    pub fn with_trigraphs(mut self, trigraphs: bool) -> Self {
+        self.trigraphs = trigraphs;
+        self
+    }
+
+    pub fn standard(&self) -> Standard {
+        self.standard
+    }
+
+    pub fn keywords(&self) -> &[Keyword] {
+        &self.keywords
+    }
+
+    pub fn puncts(&self) -> &[Punct] {
+        &self.puncts
+    }
+
+    pub fn digraphs(&self) -> bool {
+        self.digraphs
+    }
+
+    pub fn trigraphs(&self) -> bool {
+        self.trigraphs
+    }
+
+    // The keyword `ident` spells, if it is reserved
+    pub(crate) fn keyword(&self, ident: &str) -> Option<Keyword> {
+        match Keyword::from_spelling(ident) {
+            Some(keyword) => Some(keyword).filter(|k| k.index().is_some_and(|i| self.reserved[i])),
+            None => self.extensions.iter().find(|s| **s == ident).map(|s| Keyword::Extension(s)),
+        }
+    }
+
+    pub(crate) fn trie(&self) -> &Trie<Punct> {
+        &self.trie
+    }
+}
+
+impl Default for LexerConfig {
+    fn default() -> Self {
+        LexerConfig::new(LanguageProfile::C17)
+    }
+}
+
+// Digraphs go in for the punctuators they stand for
+fn build_trie(puncts: &[Punct], digraphs: bool) -> Trie<Punct> {
+    let digraphs = DIGRAPHS.iter().filter(|(_, p)| digraphs && puncts.contains(p)).copied();
+    Trie::new(puncts.iter().map(|p| (p.as_str(), *p)).chain(digraphs))
+}
@@ -0,0 +1,259 @@
+//! Concrete syntax tree. Unlike the AST it keeps every token, trivia
+//! included, so printing a tree gives back the exact source it was parsed
+//! from; formatters and refactoring tools edit this tree.
+
+use std::fmt;
+
+use crate::error::ParseError;
+use crate::lexer::Lexer;
+use crate::parser::Parser;
+use crate::token::{Span, Token};
+use crate::trivia::LosslessToken;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SyntaxKind {
+    Program,
+    FunctionDef,
+    Block,
+    // expr ; | ;
+    ExprStmt,
+    IfStmt,
+    // else stmt
+    ElseClause,
+    WhileStmt,
+    DoWhileStmt,
+    ForStmt,
+    SwitchStmt,
+    // case expr : stmt | default : stmt
+    CaseStmt,
+    // id : stmt
+    LabeledStmt,
+    GotoStmt,
+    ContinueStmt,
+    BreakStmt,
+    ReturnStmt,
+    Declaration,
+    DeclSpecifiers,
+    StructSpecifier,
+    EnumSpecifier,
+    Enumerator,
+    // declarator = initializer
+    InitDeclarator,
+    Declarator,
+    ParamList,
+    Param,
+    InitializerList,
+    // ( bool ) of if, while, do-while and switch
+    Condition,
+    CommaExpr,
+    AssignExpr,
+    ConditionalExpr,
+    // && and ||
+    LogicalExpr,
+    BinaryExpr,
+    // A prefix operator, and its operand
+    UnaryExpr,
+    PostfixExpr,
+    CastExpr,
+    SizeofExpr,
+    TypeName,
+    IndexExpr,
+    // callee ( args )
+    CallExpr,
+    MemberExpr,
+    ParenExpr,
+    NameExpr,
+    Literal,
+    /// Tokens the parser skipped to recover from an error.
+    Error,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SyntaxNode<S = String> {
+    kind: SyntaxKind,
+    children: Vec<SyntaxElement<S>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SyntaxElement<S = String> {
+    Node(SyntaxNode<S>),
+    Token(LosslessToken<S>),
+}
+
+impl<S: AsRef<str>> SyntaxNode<S> {
+    pub fn kind(&self) -> SyntaxKind {
+        self.kind
+    }
+
+    pub fn children(&self) -> &[SyntaxElement<S>] {
+        &self.children
+    }
+
+    /// Child nodes, skipping tokens.
+    pub fn nodes(&self) -> impl Iterator<Item = &SyntaxNode<S>> {
+        self.children.iter().filter_map(|child| match child {
+            SyntaxElement::Node(node) => Some(node),
+            SyntaxElement::Token(_) => None,
+        })
+    }
+
+    /// Every token under the node, in source order.
+    pub fn tokens(&self) -> Vec<&LosslessToken<S>> {
+        let mut tokens = Vec::new();
+        self.collect_tokens(&mut tokens);
+        tokens
+    }
+
+    fn collect_tokens<'a>(&'a self, out: &mut Vec<&'a LosslessToken<S>>) {
+        for child in &self.children {
+            match child {
+                SyntaxElement::Node(node) => node.collect_tokens(out),
+                SyntaxElement::Token(token) => out.push(token),
+            }
+        }
+    }
+
+    /// From the first to the last token, trivia excluded.
+    pub fn span(&self) -> Option<Span> {
+        let tokens = self.tokens();
+        let first = tokens.iter().find(|t| !matches!(t.token.token, Token::Eof))?;
+        let last = tokens.iter().rev().find(|t| !matches!(t.token.token, Token::Eof))?;
+        Some(Span::new(first.token.span.start, last.token.span.end))
+    }
+
+    pub fn into_owned(self) -> SyntaxNode {
+        SyntaxNode {
+            kind: self.kind,
+            children: self
+                .children
+                .into_iter()
+                .map(|child| match child {
+                    SyntaxElement::Node(node) => SyntaxElement::Node(node.into_owned()),
+                    SyntaxElement::Token(token) => SyntaxElement::Token(token.into_owned()),
+                })
+                .collect(),
+        }
+    }
+}
+
+// The source text of the node, trivia included
+impl<S: AsRef<str>> fmt::Display for SyntaxNode<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for child in &self.children {
+            match child {
+                SyntaxElement::Node(node) => write!(f, "{}", node)?,
+                SyntaxElement::Token(token) => write!(f, "{}", token)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Parse a whole program into a concrete syntax tree, recovering from errors
+/// like `Parser::parse_program`. The tree holds every byte of the input, the
+/// tokens the parser could not place included.
+pub fn parse(lexer: Lexer<'_>) -> (SyntaxNode<&str>, Vec<ParseError>) {
+    let trigraphs = lexer.trigraphs();
+    let tokens: Vec<_> = lexer.lossless().collect();
+    let mut parser = Parser::new(
+        tokens
+            .iter()
+            .filter(|t| !matches!(t.token.token, Token::Eof))
+            .map(|t| t.token.clone()),
+    )
+    .with_trigraphs(trigraphs)
+    .with_tree();
+    let (_, errors) = parser.parse_program();
+    let tree = parser.take_tree().expect("tree building is on");
+    (tree.build(tokens), errors)
+}
+
+// Shape of the tree, recorded by the parser as it goes; tokens are filled in
+// afterwards from the lossless token stream, in order
+enum Shape {
+    Node(SyntaxKind, Vec<Shape>),
+    Token,
+}
+
+#[derive(Default)]
+pub(crate) struct TreeBuilder {
+    // Children of the open nodes, flattened; each open node remembers where
+    // its own children start
+    children: Vec<Shape>,
+    open: Vec<(SyntaxKind, usize)>,
+}
+
+impl TreeBuilder {
+    pub(crate) fn start_node(&mut self, kind: SyntaxKind) {
+        self.open.push((kind, self.children.len()));
+    }
+
+    /// Where the next child of the current node goes, to wrap it and the
+    /// children after it in a node later on.
+    pub(crate) fn checkpoint(&self) -> usize {
+        self.children.len()
+    }
+
+    pub(crate) fn start_node_at(&mut self, checkpoint: usize, kind: SyntaxKind) {
+        debug_assert!(checkpoint >= self.open.last().map_or(0, |&(_, first)| first));
+        self.open.push((kind, checkpoint));
+    }
+
+    pub(crate) fn finish_node(&mut self) {
+        let (kind, first) = self.open.pop().expect("no node to finish");
+        let children = self.children.split_off(first);
+        self.children.push(Shape::Node(kind, children));
+    }
+
+    pub(crate) fn depth(&self) -> usize {
+        self.open.len()
+    }
+
+    /// Finish nodes left open by an error until `depth` remain.
+    pub(crate) fn finish_to(&mut self, depth: usize) {
+        while self.open.len() > depth {
+            self.finish_node();
+        }
+    }
+
+    pub(crate) fn token(&mut self) {
+        self.children.push(Shape::Token);
+    }
+
+    // Put the tokens in place. Tokens the parser never got to end up in an
+    // error node at the end of the root, before `Eof` and its trivia.
+    pub(crate) fn build<S: AsRef<str>>(mut self, tokens: Vec<LosslessToken<S>>) -> SyntaxNode<S> {
+        self.finish_to(0);
+        let mut tokens = tokens.into_iter();
+        let mut root = match self.children.pop() {
+            Some(Shape::Node(kind, children)) if self.children.is_empty() => fill(kind, children, &mut tokens),
+            _ => panic!("the parser builds a single root node"),
+        };
+
+        let mut rest = Vec::new();
+        let mut eof = None;
+        for token in tokens {
+            if let Token::Eof = token.token.token {
+                eof = Some(token);
+            } else {
+                rest.push(SyntaxElement::Token(token));
+            }
+        }
+        if !rest.is_empty() {
+            root.children.push(SyntaxElement::Node(SyntaxNode { kind: SyntaxKind::Error, children: rest }));
+        }
+        root.children.extend(eof.map(SyntaxElement::Token));
+        root
+    }
+}
+
+fn fill<S>(kind: SyntaxKind, shape: Vec<Shape>, tokens: &mut impl Iterator<Item = LosslessToken<S>>) -> SyntaxNode<S> {
+    let children = shape
+        .into_iter()
+        .filter_map(|child| match child {
+            Shape::Node(kind, children) => Some(SyntaxElement::Node(fill(kind, children, tokens))),
+            Shape::Token => tokens.next().map(SyntaxElement::Token),
+        })
+        .collect();
+    SyntaxNode { kind, children }
+}
@@ -0,0 +1,273 @@
+use std::fmt::Write;
+
+use crate::error::{ParseError, ParseErrorKind};
+use crate::token::{PositionedToken, Span, Token};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+impl Severity {
+    fn name(&self) -> &'static str {
+        match self {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        }
+    }
+
+    fn color(&self) -> &'static str {
+        match self {
+            Severity::Error => RED,
+            Severity::Warning => YELLOW,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Label {
+    pub span: Span,
+    pub message: String,
+    // The primary label is underlined with `^`, secondary ones with `-`
+    pub primary: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub message: String,
+    pub labels: Vec<Label>,
+    pub notes: Vec<String>,
+    pub help: Vec<String>,
+}
+
+impl Diagnostic {
+    pub fn new(severity: Severity, message: impl Into<String>) -> Self {
+        Diagnostic {
+            severity,
+            message: message.into(),
+            labels: Vec::new(),
+            notes: Vec::new(),
+            help: Vec::new(),
+        }
+    }
+
+    pub fn error(message: impl Into<String>) -> Self {
+        Self::new(Severity::Error, message)
+    }
+
+    pub fn warning(message: impl Into<String>) -> Self {
+        Self::new(Severity::Warning, message)
+    }
+
+    pub fn with_label(mut self, span: Span, message: impl Into<String>) -> Self {
+        let primary = self.labels.is_empty();
+        self.labels.push(Label { span, message: message.into(), primary });
+        self
+    }
+
+    pub fn with_note(mut self, note: impl Into<String>) -> Self {
+        self.notes.push(note.into());
+        self
+    }
+
+    pub fn with_help(mut self, help: impl Into<String>) -> Self {
+        self.help.push(help.into());
+        self
+    }
+
+    /// Diagnostic for a `Token::Error` produced by the lexer, `None` for any
+    /// other token.
+    pub fn from_lex_error<S>(token: &PositionedToken<S>) -> Option<Self> {
+        match &token.token {
+            Token::Error(message) => Some(Diagnostic::error(message.clone()).with_label(token.span, "invalid token")),
+            _ => None,
+        }
+    }
+
+    pub fn from_parse_error(err: &ParseError) -> Self {
+        let expected = err
+            .expected
+            .iter()
+            .map(|e| e.to_string())
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        match err.kind {
+            ParseErrorKind::UnexpectedToken => {
+                let label = match err.expected.as_slice() {
+                    [only] => format!("expected {}", only),
+                    _ => "unexpected token".to_string(),
+                };
+                Diagnostic::error(err.to_string()).with_label(err.span, label)
+            }
+            ParseErrorKind::ExpectedDeclaration => Diagnostic::error(err.to_string())
+                .with_label(err.span, "expected declaration")
+                .with_note(format!("a declaration starts with one of {}", expected)),
+            ParseErrorKind::ExpectedStatement => Diagnostic::error(err.to_string())
+                .with_label(err.span, "expected statement")
+                .with_note(format!("a statement starts with one of {}", expected)),
+            ParseErrorKind::ExpectedExpression => Diagnostic::error(err.to_string())
+                .with_label(err.span, "expected expression")
+                .with_note(format!("an expression starts with one of {}", expected)),
+            ParseErrorKind::UnexpectedEof => {
+                let label = match err.expected.as_slice() {
+                    [only] => format!("expected {}", only),
+                    _ => "unexpected end of input".to_string(),
+                };
+                Diagnostic::error(err.to_string()).with_label(err.span, label)
+            }
+            ParseErrorKind::InvalidToken => {
+                let diagnostic = Diagnostic::error(err.to_string()).with_label(err.span, "invalid token");
+                if expected.is_empty() {
+                    diagnostic
+                } else {
+                    diagnostic.with_help(format!("expected one of {}", expected))
+                }
+            }
+        }
+    }
+}
+
+const RESET: &str = "\x1b[0m";
+const BOLD: &str = "\x1b[1m";
+const RED: &str = "\x1b[1;31m";
+const YELLOW: &str = "\x1b[1;33m";
+const BLUE: &str = "\x1b[1;34m";
+const CYAN: &str = "\x1b[1;36m";
+
+/// Renders diagnostics in the style of rustc:
+///
+/// ```text
+/// error: expected `;`, found number `3`
+///  --> main.c:2:11
+///   |
+/// 2 |     i = 2 3;
+///   |           ^ expected `;`
+/// ```
+pub struct Renderer<'a> {
+    file_name: &'a str,
+    source: &'a str,
+    color: bool,
+}
+
+impl<'a> Renderer<'a> {
+    pub fn new(file_name: &'a str, source: &'a str) -> Self {
+        Renderer { file_name, source, color: false }
+    }
+
+    pub fn with_color(mut self, color: bool) -> Self {
+        self.color = color;
+        self
+    }
+
+    pub fn render_all(&self, diagnostics: &[Diagnostic]) -> String {
+        diagnostics
+            .iter()
+            .map(|d| self.render(d))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    pub fn render(&self, diagnostic: &Diagnostic) -> String {
+        let mut out = String::new();
+        let severity = diagnostic.severity;
+
+        let _ = writeln!(
+            out,
+            "{}{}",
+            self.paint(severity.color(), severity.name()),
+            self.paint(BOLD, &format!(": {}", diagnostic.message)),
+        );
+
+        let last_row = diagnostic
+            .labels
+            .iter()
+            .map(|l| l.span.end.row)
+            .max()
+            .unwrap_or(0);
+        let width = last_row.to_string().len();
+        let gutter = " ".repeat(width);
+
+        let location = match diagnostic.labels.first() {
+            Some(label) => format!("{}:{}:{}", self.file_name, label.span.start.row, label.span.start.column),
+            None => self.file_name.to_string(),
+        };
+        let _ = writeln!(out, "{}{} {}", gutter, self.paint(BLUE, "-->"), location);
+
+        if !diagnostic.labels.is_empty() {
+            let _ = writeln!(out, "{} {}", gutter, self.paint(BLUE, "|"));
+        }
+
+        let mut labels: Vec<&Label> = diagnostic.labels.iter().collect();
+        labels.sort_by_key(|l| l.span.start.offset);
+        for label in labels {
+            self.render_label(&mut out, label, severity, width);
+        }
+
+        if !diagnostic.notes.is_empty() || !diagnostic.help.is_empty() {
+            let _ = writeln!(out, "{} {}", gutter, self.paint(BLUE, "|"));
+        }
+        for note in &diagnostic.notes {
+            let _ = writeln!(out, "{} {} {}: {}", gutter, self.paint(BLUE, "="), self.paint(BOLD, "note"), note);
+        }
+        for help in &diagnostic.help {
+            let _ = writeln!(out, "{} {} {}: {}", gutter, self.paint(BLUE, "="), self.paint(CYAN, "help"), help);
+        }
+
+        out
+    }
+
+    // Print every source line the label covers with its part underlined;
+    // the message goes after the underline on the last line
+    fn render_label(&self, out: &mut String, label: &Label, severity: Severity, width: usize) {
+        let (marker, color) = if label.primary {
+            ('^', severity.color())
+        } else {
+            ('-', BLUE)
+        };
+
+        let Span { start, end } = label.span;
+        // A span ending at column 1 stops at the previous line's newline
+        let last_row = if end.row > start.row && end.column == 1 { end.row - 1 } else { end.row.max(start.row) };
+
+        // A span at the end of the input may be on the empty line after the
+        // last newline, which `lines` leaves out
+        let lines = self.source.lines().chain(std::iter::repeat(""));
+        for (row, line) in lines.enumerate().map(|(i, l)| (i + 1, l)).skip(start.row - 1).take(last_row - start.row + 1) {
+            let from = if row == start.row { start.column - 1 } else { 0 };
+            let to = if row == end.row { end.column - 1 } else { line.chars().count() };
+            let to = to.max(from + 1);
+
+            // Mirror tabs so the underline lines up with the text above it
+            let padding: String = line
+                .chars()
+                .chain(std::iter::repeat(' '))
+                .take(from)
+                .map(|c| if c == '\t' { '\t' } else { ' ' })
+                .collect();
+            let underline = marker.to_string().repeat(to - from);
+            let message = if row == last_row { label.message.as_str() } else { "" };
+
+            let number = format!("{:>width$}", row, width = width);
+            let _ = writeln!(out, "{} {} {}", self.paint(BLUE, &number), self.paint(BLUE, "|"), line);
+            let _ = writeln!(
+                out,
+                "{} {} {}{}",
+                " ".repeat(width),
+                self.paint(BLUE, "|"),
+                padding,
+                self.paint(color, format!("{} {}", underline, message).trim_end()),
+            );
+        }
+    }
+
+    fn paint(&self, color: &str, text: &str) -> String {
+        if self.color {
+            format!("{}{}{}", color, text, RESET)
+        } else {
+            text.to_string()
+        }
+    }
+}
@@ -0,0 +1,218 @@
+use std::fmt;
+
+use crate::token::{Keyword, Punct, Span, Token};
+
+/// A token (or class of tokens) the parser would have accepted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Expected {
+    Keyword(Keyword),
+    Symbol(Punct),
+    Identifier,
+    Number,
+    CharLiteral,
+    StringLiteral,
+    // A prefix operator of the parser's `OperatorTable`, starting an expression
+    Operator,
+    // A keyword that starts a declaration or type name
+    Type,
+    EndOfInput,
+}
+
+impl Expected {
+    pub fn matches<S: AsRef<str>>(&self, token: &Token<S>) -> bool {
+        match (self, token) {
+            (Expected::Keyword(k), Token::Keyword(s)) => k == s,
+            (Expected::Symbol(p), Token::Symbol(s)) => p == s,
+            (Expected::Identifier, Token::Identifier(_)) => true,
+            (Expected::Number, Token::Number { .. }) => true,
+            (Expected::CharLiteral, Token::CharLiteral { .. }) => true,
+            (Expected::StringLiteral, Token::StringLiteral { .. }) => true,
+            (Expected::Operator, Token::Symbol(_)) => true,
+            (Expected::Type, Token::Keyword(k)) => TYPE_KEYWORDS.contains(k) || DECL_KEYWORDS.contains(k),
+            (Expected::EndOfInput, Token::Eof) => true,
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for Expected {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Expected::Keyword(k) => write!(f, "`{}`", k),
+            Expected::Symbol(p) => write!(f, "`{}`", p),
+            Expected::Identifier => write!(f, "identifier"),
+            Expected::Number => write!(f, "number"),
+            Expected::CharLiteral => write!(f, "character literal"),
+            Expected::StringLiteral => write!(f, "string literal"),
+            Expected::Operator => write!(f, "operator"),
+            Expected::Type => write!(f, "type"),
+            Expected::EndOfInput => write!(f, "end of input"),
+        }
+    }
+}
+
+// FIRST(item) = FIRST(declaration) and { of the teaching grammar's block.
+// Function definitions start like declarations.
+pub const FIRST_ITEM: &[Expected] = &[Expected::Type, Expected::Symbol(Punct::LBrace)];
+
+// FIRST(stmt) = { if, while, do, for, switch, case, default, goto,
+// continue, break, return, {, ; }, FIRST(declaration) and FIRST(expr)
+pub const FIRST_STMT: &[Expected] = &[
+    Expected::Identifier,
+    Expected::Type,
+    Expected::Keyword(Keyword::If),
+    Expected::Keyword(Keyword::While),
+    Expected::Keyword(Keyword::Do),
+    Expected::Keyword(Keyword::For),
+    Expected::Keyword(Keyword::Switch),
+    Expected::Keyword(Keyword::Case),
+    Expected::Keyword(Keyword::Default),
+    Expected::Keyword(Keyword::Goto),
+    Expected::Keyword(Keyword::Continue),
+    Expected::Keyword(Keyword::Break),
+    Expected::Keyword(Keyword::Return),
+    Expected::Symbol(Punct::LBrace),
+    Expected::Symbol(Punct::Semi),
+    Expected::Symbol(Punct::LParen),
+    Expected::Number,
+    Expected::CharLiteral,
+    Expected::StringLiteral,
+    Expected::Keyword(Keyword::Sizeof),
+    Expected::Operator,
+];
+
+// FIRST(expr) = { (, id, num, char, string, sizeof } and the prefix operators
+pub const FIRST_EXPR: &[Expected] = &[
+    Expected::Symbol(Punct::LParen),
+    Expected::Identifier,
+    Expected::Number,
+    Expected::CharLiteral,
+    Expected::StringLiteral,
+    Expected::Keyword(Keyword::Sizeof),
+    Expected::Operator,
+];
+
+// Keywords that start a type name: the type specifiers and qualifiers.
+// Typedef names start one too, which only the parser can tell.
+pub const TYPE_KEYWORDS: &[Keyword] = &[
+    Keyword::Void,
+    Keyword::Char,
+    Keyword::Short,
+    Keyword::Int,
+    Keyword::Long,
+    Keyword::Float,
+    Keyword::Double,
+    Keyword::Signed,
+    Keyword::Unsigned,
+    Keyword::_Bool,
+    Keyword::Bool,
+    Keyword::_Complex,
+    Keyword::Struct,
+    Keyword::Union,
+    Keyword::Enum,
+    Keyword::Const,
+    Keyword::Volatile,
+    Keyword::Restrict,
+    Keyword::_Atomic,
+];
+
+// Keywords that only start a declaration: storage classes and function
+// specifiers
+pub const DECL_KEYWORDS: &[Keyword] = &[
+    Keyword::Typedef,
+    Keyword::Extern,
+    Keyword::Static,
+    Keyword::_ThreadLocal,
+    Keyword::ThreadLocal,
+    Keyword::Auto,
+    Keyword::Register,
+    Keyword::Inline,
+    Keyword::_Noreturn,
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParseErrorKind {
+    /// A specific token was required but something else was found.
+    UnexpectedToken,
+    /// No declaration or function definition can start with the found token.
+    ExpectedDeclaration,
+    /// No statement can start with the found token.
+    ExpectedStatement,
+    /// No expression can start with the found token.
+    ExpectedExpression,
+    /// The lexer could not make sense of the input here.
+    InvalidToken,
+    /// The input ended in the middle of a construct.
+    UnexpectedEof,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    pub kind: ParseErrorKind,
+    pub span: Span,
+    pub expected: Vec<Expected>,
+    pub found: Box<Token>,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.kind {
+            ParseErrorKind::UnexpectedToken => {
+                write!(f, "expected ")?;
+                write_expected(f, &self.expected)?;
+            }
+            ParseErrorKind::ExpectedDeclaration => write!(f, "expected declaration")?,
+            ParseErrorKind::ExpectedStatement => write!(f, "expected statement")?,
+            ParseErrorKind::ExpectedExpression => write!(f, "expected expression")?,
+            ParseErrorKind::InvalidToken => {
+                if let Token::Error(message) = self.found.as_ref() {
+                    return write!(f, "invalid token: {}", message);
+                }
+                write!(f, "invalid token")?;
+            }
+            ParseErrorKind::UnexpectedEof => {
+                write!(f, "unexpected end of input")?;
+                if !self.expected.is_empty() {
+                    write!(f, ", expected ")?;
+                    write_expected(f, &self.expected)?;
+                }
+                return Ok(());
+            }
+        }
+        write!(f, ", found ")?;
+        write_found(f, &self.found)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+fn write_expected(f: &mut fmt::Formatter<'_>, expected: &[Expected]) -> fmt::Result {
+    match expected {
+        [] => write!(f, "nothing"),
+        [only] => write!(f, "{}", only),
+        [init @ .., last] => {
+            write!(f, "one of ")?;
+            for (i, e) in init.iter().enumerate() {
+                if i > 0 {
+                    write!(f, ", ")?;
+                }
+                write!(f, "{}", e)?;
+            }
+            write!(f, " or {}", last)
+        }
+    }
+}
+
+fn write_found(f: &mut fmt::Formatter<'_>, found: &Token) -> fmt::Result {
+    match found {
+        Token::Keyword(s)                => write!(f, "keyword `{}`", s),
+        Token::Symbol(s)                 => write!(f, "`{}`", s),
+        Token::Identifier(s)             => write!(f, "identifier `{}`", s),
+        Token::Number { literal, .. }    => write!(f, "number `{}`", literal),
+        Token::CharLiteral { raw, .. }   => write!(f, "character literal '{}'", raw),
+        Token::StringLiteral { raw, .. } => write!(f, "string literal \"{}\"", raw),
+        Token::Comment(_)                => write!(f, "comment"),
+        Token::Error(_)                  => write!(f, "invalid token"),
+        Token::Eof                       => write!(f, "end of input"),
+    }
+}
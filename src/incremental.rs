@@ -0,0 +1,292 @@
+use std::collections::HashMap;
+use std::ops::Range;
+
+use crate::ast::{Item, Program, Stmt};
+use crate::config::LexerConfig;
+use crate::error::{Expected, ParseError};
+use crate::lexer::Lexer;
+use crate::parser::Parser;
+use crate::source::LOOKAHEAD;
+use crate::token::{Position, PositionedToken, Span, Token};
+
+// How many chars past the end of a token the lexer may look to decide where
+// the token ends: `1'2` and `..` take two to tell
+const LEXER_LOOKAHEAD: usize = 2;
+
+/// Replace the bytes in `range` of the text with `text`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TextEdit {
+    pub range: Range<usize>,
+    pub text: String,
+}
+
+impl TextEdit {
+    pub fn new(range: Range<usize>, text: impl Into<String>) -> Self {
+        TextEdit { range, text: text.into() }
+    }
+}
+
+// What the parser made of a top-level item or a statement of a top-level
+// block; the item or statement itself lives in the program
+#[derive(Debug, Clone)]
+pub(crate) struct ParsedStmt {
+    // Counting the tokens the parser sees, i.e. without comments
+    pub(crate) first_token: usize,
+    pub(crate) token_count: usize,
+    // Errors as if the statement were parsed on its own
+    pub(crate) errors: Vec<ParseError>,
+    // The parser's expected set going in and coming out
+    pub(crate) expected_before: Vec<Expected>,
+    pub(crate) expected_after: Vec<Expected>,
+    pub(crate) names: Names,
+}
+
+// Which typedef names a statement saw and declared: `T * x;` declares `x`
+// if `T` is a type and multiplies otherwise
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Names {
+    // Scope depth of the statement; only what it declares there outlives it
+    pub(crate) depth: usize,
+    // Identifiers looked up and whether they were typedef names
+    pub(crate) lookups: Vec<(String, bool)>,
+    pub(crate) declared: Vec<(String, bool)>,
+}
+
+// What the cache reuses: items as a whole, but the statements of a
+// top-level block one by one, since the block is all of a teaching program
+#[derive(Debug, Clone)]
+pub(crate) enum Cached {
+    Item(Item),
+    Stmt(Stmt),
+}
+
+/// Top-level items and statements of the last parse that can stand in for
+/// parsing again, and what the current parse makes of them.
+#[derive(Default)]
+pub(crate) struct StmtCache {
+    // By first token in the new token stream
+    reusable: HashMap<usize, (Cached, ParsedStmt)>,
+    parsed: Vec<ParsedStmt>,
+    reused: usize,
+}
+
+impl StmtCache {
+    // A statement (or item, unless `stmt`) starting at `first_token`, if the
+    // parser comes to it in the same state as last time: the same expected
+    // set and the same typedef names, as far as the statement looked
+    pub(crate) fn take(
+        &mut self,
+        first_token: usize,
+        stmt: bool,
+        expected: &[Expected],
+        is_typedef: impl Fn(&str) -> bool,
+    ) -> Option<(Cached, ParsedStmt)> {
+        match self.reusable.get(&first_token) {
+            Some((cached, parsed))
+                if matches!(cached, Cached::Stmt(_)) == stmt
+                    && parsed.expected_before == expected
+                    && parsed.names.lookups.iter().all(|(name, typedef)| is_typedef(name) == *typedef) =>
+            {
+                self.reused += 1;
+                self.reusable.remove(&first_token)
+            }
+            _ => None,
+        }
+    }
+
+    pub(crate) fn push(&mut self, parsed: ParsedStmt) {
+        self.parsed.push(parsed);
+    }
+}
+
+/// A source text kept lexed and parsed across edits. An edit re-lexes from
+/// the last token boundary before it until the tokens line up with the old
+/// ones again, and top-level items whose tokens did not change are taken
+/// over instead of parsed again, as are the statements of a top-level
+/// block. The result is always the same as lexing and parsing the new text
+/// from scratch.
+pub struct Document {
+    config: LexerConfig,
+    source: String,
+    tokens: Vec<PositionedToken>,
+    program: Program,
+    errors: Vec<ParseError>,
+    // Parallel to the program's items, with each top-level block's
+    // statements in place of the block
+    stmts: Vec<ParsedStmt>,
+    relexed: usize,
+    reused: usize,
+}
+
+impl Document {
+    pub fn new(source: impl Into<String>) -> Self {
+        Self::with_config(source, LexerConfig::default())
+    }
+
+    pub fn with_config(source: impl Into<String>, config: LexerConfig) -> Self {
+        let source = source.into();
+        let tokens = Lexer::new_with_config(&source, &config).tokenize();
+        let (program, errors, cache) = parse(&tokens, config.trigraphs(), StmtCache::default());
+        Document {
+            config,
+            relexed: tokens.len(),
+            source,
+            tokens,
+            program,
+            errors,
+            stmts: cache.parsed,
+            reused: 0,
+        }
+    }
+
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    pub fn tokens(&self) -> &[PositionedToken] {
+        &self.tokens
+    }
+
+    pub fn program(&self) -> &Program {
+        &self.program
+    }
+
+    pub fn errors(&self) -> &[ParseError] {
+        &self.errors
+    }
+
+    /// Number of tokens the last edit lexed again.
+    pub fn relexed(&self) -> usize {
+        self.relexed
+    }
+
+    /// Number of top-level items and statements the last edit did not parse
+    /// again.
+    pub fn reused(&self) -> usize {
+        self.reused
+    }
+
+    /// Apply `edit` and bring tokens, program and errors up to date.
+    ///
+    /// Panics if the range is out of bounds or not on char boundaries, like
+    /// `String::replace_range`.
+    pub fn edit(&mut self, edit: TextEdit) {
+        let TextEdit { range, text } = edit;
+
+        // Tokens that end well before the edit stay as they are, lexing
+        // restarts right after them
+        let lexer = Lexer::new_with_config(&self.source, &self.config);
+        let keep = self
+            .tokens
+            .partition_point(|t| lexer.lookahead_end(t.span.end.offset, LEXER_LOOKAHEAD) <= range.start);
+        let restart = match keep {
+            0 => Position { offset: 0, row: 1, column: 1 },
+            _ => self.tokens[keep - 1].span.end,
+        };
+
+        // Past the edit, positions move from `old_end` to `new_end`
+        let old_end = advance_to(&self.source, restart, range.end);
+        self.source.replace_range(range.clone(), &text);
+        let new_end = advance_to(&self.source, restart, range.start + text.len());
+        let shift = move |p: Position| {
+            let offset = p.offset - old_end.offset + new_end.offset;
+            if p.row == old_end.row {
+                Position { offset, row: new_end.row, column: p.column - old_end.column + new_end.column }
+            } else {
+                Position { offset, row: p.row - old_end.row + new_end.row, column: p.column }
+            }
+        };
+        let shift_token = |t: &PositionedToken| PositionedToken {
+            token: t.token.clone(),
+            span: Span::new(shift(t.span.start), shift(t.span.end)),
+        };
+
+        // Re-lex until a token starts past the edit where an old one did:
+        // the lexer carries no state from token to token, so everything
+        // after it is the old tokens moved
+        let old_tokens = std::mem::take(&mut self.tokens);
+        let mut tokens = old_tokens[..keep].to_vec();
+        let mut tail = None;
+        for token in Lexer::new_with_config(&self.source, &self.config).starting_at(restart) {
+            let token = token.into_owned();
+            if token.span.start.offset >= new_end.offset {
+                let old_offset = token.span.start.offset - new_end.offset + old_end.offset;
+                if let Ok(i) = old_tokens.binary_search_by_key(&old_offset, |t| t.span.start.offset) {
+                    if shift_token(&old_tokens[i]) == token {
+                        tail = Some(i);
+                        break;
+                    }
+                }
+            }
+            tokens.push(token);
+        }
+        self.relexed = tokens.len() - keep;
+        let new_tail = tokens.len();
+        if let Some(old_tail) = tail {
+            tokens.extend(old_tokens[old_tail..].iter().map(shift_token));
+        }
+
+        // The parser counts tokens without comments
+        let seen = |tokens: &[PositionedToken]| tokens.iter().filter(|t| !matches!(t.token, Token::Comment(_))).count();
+        let prefix = seen(&old_tokens[..keep]);
+        let tail = tail.map(|old_tail| (seen(&old_tokens[..old_tail]), seen(&tokens[..new_tail])));
+
+        // A statement depends on its tokens and the ones the parser peeked at
+        // after it; if none of them changed it parses the same
+        let mut cache = StmtCache::default();
+        let mut stmts = Vec::new();
+        for item in std::mem::take(&mut self.program.items) {
+            match item {
+                Item::Block(block) => stmts.extend(block.stmts.into_iter().map(Cached::Stmt)),
+                item => stmts.push(Cached::Item(item)),
+            }
+        }
+        for (mut stmt, mut parsed) in stmts.into_iter().zip(std::mem::take(&mut self.stmts)) {
+            if parsed.first_token + parsed.token_count + LOOKAHEAD <= prefix {
+                cache.reusable.insert(parsed.first_token, (stmt, parsed));
+            } else if let Some((old_tail, new_tail)) = tail.filter(|&(old_tail, _)| parsed.first_token >= old_tail) {
+                parsed.first_token = parsed.first_token - old_tail + new_tail;
+                match &mut stmt {
+                    Cached::Item(item) => item.map_positions(&shift),
+                    Cached::Stmt(stmt) => stmt.map_positions(&shift),
+                }
+                for err in &mut parsed.errors {
+                    err.span = Span::new(shift(err.span.start), shift(err.span.end));
+                }
+                cache.reusable.insert(parsed.first_token, (stmt, parsed));
+            }
+        }
+
+        let (program, errors, cache) = parse(&tokens, self.config.trigraphs(), cache);
+        self.tokens = tokens;
+        self.program = program;
+        self.errors = errors;
+        self.stmts = cache.parsed;
+        self.reused = cache.reused;
+    }
+}
+
+fn parse(tokens: &[PositionedToken], trigraphs: bool, cache: StmtCache) -> (Program, Vec<ParseError>, StmtCache) {
+    let mut parser = Parser::new(tokens.iter().cloned())
+        .with_comments(false)
+        .with_trigraphs(trigraphs)
+        .with_cache(cache);
+    let (program, errors) = parser.parse_program();
+    let cache = parser.take_cache().expect("parsed with a cache");
+    (program, errors, cache)
+}
+
+// The position of `offset`, counting from `from` like the lexer does
+fn advance_to(text: &str, from: Position, offset: usize) -> Position {
+    let mut position = from;
+    for c in text[from.offset..offset].chars() {
+        if c == '\n' {
+            position.row += 1;
+            position.column = 1;
+        } else {
+            position.column += 1;
+        }
+    }
+    position.offset = offset;
+    position
+}
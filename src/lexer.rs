@@ -1,39 +1,249 @@
+use std::borrow::Cow;
+use std::sync::OnceLock;
+
+use crate::config::{LexerConfig, Standard};
+use crate::number::NumberError;
 use crate::token::*;
-pub struct Lexer {
-    input: Vec<char>,
-    position: usize,
+use crate::trivia::Lossless;
+
+// Bytes that are never part of a trigraph or line splice
+fn is_plain(b: u8) -> bool {
+    b.is_ascii() && b != b'\\' && b != b'?'
+}
+
+// The char `??c` stands for
+fn trigraph(c: u8) -> Option<char> {
+    Some(match c {
+        b'=' => '#',
+        b'(' => '[',
+        b'/' => '\\',
+        b')' => ']',
+        b'\'' => '^',
+        b'<' => '{',
+        b'!' => '|',
+        b'>' => '}',
+        b'-' => '~',
+        _ => return None,
+    })
+}
+
+// `text` as the phases after line splicing see it: trigraphs replaced if
+// `trigraphs` is set, and line splices removed
+pub(crate) fn translate(text: &str, trigraphs: bool) -> Cow<'_, str> {
+    if !text.bytes().any(|b| b == b'\\' || trigraphs && b == b'?') {
+        return Cow::Borrowed(text);
+    }
+    let mut lexer = Lexer::new(text);
+    lexer.trigraphs = trigraphs;
+    let mut out = String::with_capacity(text.len());
+    while let Some(c) = lexer.advance() {
+        out.push(c);
+    }
+    Cow::Owned(out)
+}
+
+fn default_config() -> &'static LexerConfig {
+    static CONFIG: OnceLock<LexerConfig> = OnceLock::new();
+    CONFIG.get_or_init(LexerConfig::default)
+}
+
+// A decoded escape sequence: octal and hex escapes name a single byte,
+// universal character names a code point
+enum Escaped {
+    Byte(u8),
+    Char(char),
+}
+
+/// Scans the source text in place; the tokens it yields borrow their text
+/// from the input instead of allocating.
+pub struct Lexer<'src> {
+    input: &'src str,
+    position: usize, // byte offset of the next char
     row: usize,
     column: usize,
+    config: &'src LexerConfig,
+    standard: Standard,
+    trigraphs: bool,
 }
 
-impl Lexer {
-    pub fn new(input: &str) -> Self {
+impl<'src> Lexer<'src> {
+    /// A lexer for C17.
+    pub fn new(input: &'src str) -> Self {
+        Self::new_with_config(input, default_config())
+    }
+
+    pub fn new_with_config(input: &'src str, config: &'src LexerConfig) -> Self {
         Lexer {
-            input: input.chars().collect(),
+            input,
             position: 0,
             row: 1,
             column: 1,
+            config,
+            standard: config.standard(),
+            trigraphs: config.trigraphs(),
+        }
+    }
+
+    pub(crate) fn trigraphs(&self) -> bool {
+        self.trigraphs
+    }
+
+    // Continue at `position`, a token boundary of an earlier run over
+    // (mostly) the same input
+    pub(crate) fn starting_at(mut self, position: Position) -> Self {
+        self.position = position.offset;
+        self.row = position.row;
+        self.column = position.column;
+        self
+    }
+
+    /// Follow the given revision of the C standard instead of the config's.
+    pub fn with_standard(mut self, standard: Standard) -> Self {
+        self.standard = standard;
+        self
+    }
+
+    // Translation phases 1 and 2 happen on the fly: `peek` and `advance`
+    // see trigraphs (when enabled) replaced and line splices removed, while
+    // positions stay physical. Token texts are slices of the input and keep
+    // both as spelled.
+
+    // Past the line splices at `offset`, if any
+    pub(crate) fn skip_splices_from(&self, mut offset: usize) -> usize {
+        let bytes = self.input.as_bytes();
+        loop {
+            if bytes.get(offset).is_some_and(|&b| is_plain(b)) {
+                return offset;
+            }
+            let rest = &bytes[offset..];
+            let len = if rest.starts_with(b"\\\n") {
+                2
+            } else if rest.starts_with(b"\\\r\n") {
+                3
+            } else if self.trigraphs && rest.starts_with(b"??/\n") {
+                4
+            } else if self.trigraphs && rest.starts_with(b"??/\r\n") {
+                5
+            } else {
+                return offset;
+            };
+            offset += len;
+        }
+    }
+
+    // The char at `offset`, splices already skipped, and how many bytes it
+    // takes in the input
+    fn char_at(&self, offset: usize) -> Option<(char, usize)> {
+        let bytes = self.input.as_bytes();
+        match bytes.get(offset) {
+            Some(b'?') if self.trigraphs && bytes.get(offset + 1) == Some(&b'?') => {
+                match bytes.get(offset + 2).and_then(|&b| trigraph(b)) {
+                    Some(c) => Some((c, 3)),
+                    None => Some(('?', 1)),
+                }
+            }
+            Some(&b) if b.is_ascii() => Some((b as char, 1)),
+            Some(_) => self.input[offset..].chars().next().map(|c| (c, c.len_utf8())),
+            None => None,
         }
     }
 
     fn peek(&self) -> Option<char> {
-        self.input.get(self.position).copied()
+        match self.input.as_bytes().get(self.position) {
+            Some(&b) if is_plain(b) => Some(b as char),
+            Some(_) => self.char_at(self.skip_splices_from(self.position)).map(|(c, _)| c),
+            None => None,
+        }
+    }
+
+    // The byte after the current char, enough to look for ASCII delimiters
+    fn peek_next_byte(&self) -> Option<u8> {
+        let bytes = self.input.as_bytes();
+        match (bytes.get(self.position), bytes.get(self.position + 1)) {
+            (Some(&b), next) if is_plain(b) && next.is_none_or(|&next| next != b'\\' && next != b'?') => {
+                next.copied()
+            }
+            _ => {
+                let current = self.skip_splices_from(self.position);
+                let next = self.skip_splices_from(current + self.char_at(current)?.1);
+                let (c, _) = self.char_at(next)?;
+                Some(if c.is_ascii() { c as u8 } else { bytes[next] })
+            }
+        }
+    }
+
+    // At a `\n`, or the `\r` of a `\r\n`, so that neither ends up in a token
+    fn at_line_end(&self) -> bool {
+        match self.peek() {
+            Some('\n') => true,
+            Some('\r') => self.peek_next_byte() == Some(b'\n'),
+            _ => false,
+        }
+    }
+
+    fn slice(&self, start: usize) -> &'src str {
+        &self.input[start..self.position]
+    }
+
+    // Step over line splices, each of which ends a physical line
+    fn skip_splices(&mut self) {
+        let end = self.skip_splices_from(self.position);
+        if end > self.position {
+            self.row += self.input[self.position..end].matches('\n').count();
+            self.column = 1;
+            self.position = end;
+        }
     }
 
     fn advance(&mut self) -> Option<char> {
-        let current = self.peek();
+        let (c, len) = match self.input.as_bytes().get(self.position) {
+            Some(&b) if is_plain(b) => (b as char, 1),
+            _ => {
+                self.skip_splices();
+                self.char_at(self.position)?
+            }
+        };
 
-        if let Some(c) = current {
-            if c == '\n' {
-                self.row += 1;
-                self.column = 1;
-            } else {
-                self.column += 1;
+        if c == '\n' {
+            self.row += 1;
+            self.column = 1;
+        } else {
+            // A trigraph takes three columns
+            self.column += if len == c.len_utf8() { 1 } else { len };
+        }
+        self.position += len;
+
+        Some(c)
+    }
+
+    // How far lexing a token that ends at `offset` may look into the input
+    // to see `chars` more chars, splices before them included. Past the end
+    // of the input, in case text is added there.
+    pub(crate) fn lookahead_end(&self, mut offset: usize, chars: usize) -> usize {
+        let mut end = offset;
+        for _ in 0..chars {
+            if offset < self.input.len() {
+                offset = self.skip_splices_from(offset);
             }
-            self.position += 1;
+            let len = self.char_at(offset).map_or(1, |(_, len)| len);
+            // A `\` or `?` may turn out to start a splice or trigraph
+            let seen = match self.input.as_bytes().get(offset) {
+                Some(b'\\') => 3,
+                Some(b'?') if self.trigraphs => 5,
+                _ => len,
+            };
+            end = end.max(offset + seen);
+            offset += len;
         }
+        end
+    }
 
-        current
+    fn current_position(&self) -> Position {
+        Position {
+            offset: self.position,
+            row: self.row,
+            column: self.column,
+        }
     }
 
     fn skip_whitespace(&mut self) {
@@ -46,12 +256,11 @@ impl Lexer {
         }
     }
 
-    fn parse_identifier_or_keyword(&mut self) -> Token {
-        let mut ident = String::new();
+    fn parse_identifier_or_keyword(&mut self) -> Token<&'src str> {
+        let start = self.position;
 
         if let Some(c) = self.peek() {
             if c.is_alphabetic() || c == '_' {
-                ident.push(c);
                 self.advance();
             } else {
                 return Token::Error(format!("Invalid start of identifier: {}", c));
@@ -60,69 +269,99 @@ impl Lexer {
 
         while let Some(c) = self.peek() {
             if c.is_alphanumeric() || c == '_' {
-                ident.push(c);
                 self.advance();
             } else {
                 break;
             }
         }
 
-        if KEYWORDS.contains(&ident.as_str()) {
-            Token::Keyword(ident)
-        } else {
-            Token::Identifier(ident)
+        let ident = self.slice(start);
+        match self.config.keyword(&translate(ident, self.trigraphs)) {
+            Some(keyword) => Token::Keyword(keyword),
+            None => Token::Identifier(ident),
+        }
+    }
+
+    // Consume digits of `radix`, and C23 digit separators between them.
+    // Returns whether there was any digit.
+    fn parse_digits(&mut self, radix: u32) -> bool {
+        let start = self.position;
+        while let Some(c) = self.peek() {
+            if c.is_digit(radix) {
+                self.advance();
+            } else if c == '\'' && self.separator_follows(radix) {
+                self.advance(); // Consume separator
+            } else {
+                break;
+            }
         }
+        self.position > start
+    }
+
+    // A `'` between two digits is a separator in C23, anything else starts a
+    // character literal
+    fn separator_follows(&self, radix: u32) -> bool {
+        self.standard >= Standard::C23
+            && self.position > 0
+            && (self.input.as_bytes()[self.position - 1] as char).is_digit(radix)
+            && self.peek_next_byte().is_some_and(|b| (b as char).is_digit(radix))
     }
 
-    fn parse_number(&mut self) -> Token {
-        let mut number = String::new();
+    fn parse_number(&mut self) -> Token<&'src str> {
+        let start = self.position;
         let mut kind = NumberKind::Integer;
         let mut base = NumberBase::Decimal;
-        let suffix;
+        let mut malformed = None;
 
         // Check for hexadecimal, octal, or binary literals
         if self.peek() == Some('0') {
-            number.push(self.advance().unwrap()); // Consume '0'
+            self.advance(); // Consume '0'
             if let Some(c) = self.peek() {
                 match c {
                     'x' | 'X' => {
                         base = NumberBase::Hexadecimal;
-                        number.push(self.advance().unwrap()); // Consume 'x' or 'X'
-                        while let Some(c) = self.peek() {
-                            if c.is_digit(16) {
-                                number.push(c);
-                                self.advance();
+                        self.advance(); // Consume 'x' or 'X'
+                        let mut digits = self.parse_digits(16);
+                        // Hexadecimal floating constants, 0x1.8p3
+                        if let Some('.' | 'p' | 'P') = self.peek() {
+                            kind = NumberKind::FloatingPoint;
+                            if self.peek() == Some('.') {
+                                self.advance(); // Consume '.'
+                                digits |= self.parse_digits(16);
+                            }
+                            if let Some('p' | 'P') = self.peek() {
+                                if !self.parse_exponent() {
+                                    malformed = Some(NumberError::MissingExponent);
+                                }
                             } else {
-                                break;
+                                malformed = Some(NumberError::MissingBinaryExponent);
                             }
                         }
+                        if !digits {
+                            malformed = Some(NumberError::NoDigits { base });
+                        }
                     }
                     'b' | 'B' => {
                         base = NumberBase::Binary;
-                        number.push(self.advance().unwrap()); // Consume 'b' or 'B'
-                        while let Some(c) = self.peek() {
-                            if c == '0' || c == '1' {
-                                number.push(c);
-                                self.advance();
-                            } else {
-                                break;
-                            }
+                        self.advance(); // Consume 'b' or 'B'
+                        if !self.parse_digits(2) {
+                            malformed = Some(NumberError::NoDigits { base });
                         }
                     }
-                    '0'..='7' => {
+                    // Take 8 and 9 too: `089` is one (invalid) octal
+                    // constant and `089.5` a valid decimal float
+                    '0'..='9' | '\'' if c != '\'' || self.separator_follows(10) => {
                         base = NumberBase::Octal;
-                        while let Some(c) = self.peek() {
-                            if c >= '0' && c <= '7' {
-                                number.push(c);
-                                self.advance();
-                            } else {
-                                break;
-                            }
+                        self.parse_digits(10);
+                        if let Some('.' | 'e' | 'E') = self.peek() {
+                            base = NumberBase::Decimal;
+                            kind = NumberKind::FloatingPoint;
+                            malformed = self.parse_fraction_and_exponent();
                         }
                     }
                     '.' | 'e' | 'E' => {
                         kind = NumberKind::FloatingPoint;
-                        self.parse_fraction_and_exponent(&mut number);
+                        malformed = self.parse_fraction_and_exponent();
                     }
                     _ => {
                         // It's just '0'
@@ -130,67 +369,103 @@ impl Lexer {
                 }
             }
         } else {
-            // Parse decimal integer or floating point
-            while let Some(c) = self.peek() {
-                if c.is_digit(10) {
-                    number.push(c);
-                    self.advance();
-                } else if c == '.' || c == 'e' || c == 'E' {
-                    kind = NumberKind::FloatingPoint;
-                    self.parse_fraction_and_exponent(&mut number);
-                    break;
-                } else {
-                    break;
-                }
+            // Parse decimal integer or floating point, possibly `.5`
+            self.parse_digits(10);
+            if let Some('.' | 'e' | 'E') = self.peek() {
+                kind = NumberKind::FloatingPoint;
+                malformed = self.parse_fraction_and_exponent();
             }
         }
 
+        let literal = self.slice(start);
+
         // Check for suffixes
-        if kind == NumberKind::Integer {
-            suffix = self.parse_integer_suffix();
+        let suffix = if kind == NumberKind::Integer {
+            self.parse_integer_suffix()
         } else {
-            suffix = self.parse_floating_point_suffix();
+            self.parse_floating_point_suffix()
+        };
+
+        // Whatever else a C pp-number takes belongs to this token too, so
+        // `123abc` is one bad number rather than a number and an identifier
+        let suffix_start = start + literal.len();
+        let valid_end = self.position;
+        self.skip_pp_number();
+
+        if let Some(err) = malformed {
+            return Token::Error(err.to_string());
+        }
+        if self.position > valid_end {
+            let rest = &self.input[suffix_start..self.position];
+            if rest.starts_with('.') {
+                return Token::Error("too many decimal points in number".to_string());
+            }
+            let constant = match kind {
+                NumberKind::Integer => "integer",
+                NumberKind::FloatingPoint => "floating",
+            };
+            return Token::Error(format!("invalid suffix '{}' on {} constant", rest, constant));
+        }
+
+        if base == NumberBase::Binary && self.standard < Standard::C23 {
+            return Token::Error("binary constants require C23".to_string());
+        }
+        if base == NumberBase::Hexadecimal && kind == NumberKind::FloatingPoint && self.standard < Standard::C99 {
+            return Token::Error("hexadecimal floating constants require C99".to_string());
         }
 
         Token::Number {
-            literal: number,
+            literal,
+            raw: self.slice(start),
             kind,
             base,
             suffix,
         }
     }
 
-    fn parse_fraction_and_exponent(&mut self, number: &mut String) {
+    fn parse_fraction_and_exponent(&mut self) -> Option<NumberError> {
         // Parse fractional part
         if self.peek() == Some('.') {
-            number.push(self.advance().unwrap()); // Consume '.'
-            while let Some(c) = self.peek() {
-                if c.is_digit(10) {
-                    number.push(c);
-                    self.advance();
-                } else {
-                    break;
-                }
-            }
+            self.advance(); // Consume '.'
+            self.parse_digits(10);
         }
 
         // Parse exponent part
-        if let Some(c) = self.peek() {
-            if c == 'e' || c == 'E' {
-                number.push(self.advance().unwrap()); // Consume 'e' or 'E'
-                if let Some(c) = self.peek() {
-                    if c == '+' || c == '-' {
-                        number.push(self.advance().unwrap()); // Consume '+' or '-'
-                    }
+        if let Some('e' | 'E') = self.peek() {
+            if !self.parse_exponent() {
+                return Some(NumberError::MissingExponent);
+            }
+        }
+        None
+    }
+
+    // Returns whether the exponent has digits
+    fn parse_exponent(&mut self) -> bool {
+        self.advance(); // Consume 'e', 'E', 'p' or 'P'
+        if let Some('+' | '-') = self.peek() {
+            self.advance(); // Consume '+' or '-'
+        }
+        self.parse_digits(10)
+    }
+
+    // C17 6.4.8: pp-number continues with digits, identifier characters,
+    // `.`, a sign after an exponent letter, and in C23 `'` before a digit
+    // or letter
+    fn skip_pp_number(&mut self) {
+        while let Some(c) = self.peek() {
+            match c {
+                '+' | '-' if matches!(self.input.as_bytes()[self.position - 1], b'e' | b'E' | b'p' | b'P') => {
+                    self.advance();
                 }
-                while let Some(c) = self.peek() {
-                    if c.is_digit(10) {
-                        number.push(c);
-                        self.advance();
-                    } else {
-                        break;
-                    }
+                '\'' if self.standard >= Standard::C23
+                    && self.peek_next_byte().is_some_and(|b| b.is_ascii_alphanumeric() || b == b'_') =>
+                {
+                    self.advance();
                 }
+                c if c.is_alphanumeric() || c == '_' || c == '.' => {
+                    self.advance();
+                }
+                _ => break,
             }
         }
     }
@@ -198,6 +473,7 @@ impl Lexer {
     fn parse_integer_suffix(&mut self) -> Option<NumberSuffix> {
         let mut unsigned = false;
         let mut long_count = 0;
+        let mut bit_precise = false;
 
         loop {
             match self.peek() {
@@ -208,23 +484,32 @@ impl Lexer {
                     unsigned = true;
                     self.advance();
                 }
-                Some('l') | Some('L') => {
-                    let mut l_count = 0;
-                    while let Some('l') | Some('L') = self.peek() {
-                        l_count += 1;
+                // 'l', 'll' or 'LL', but not 'lL' or a second long suffix
+                Some(l @ ('l' | 'L')) if !bit_precise && long_count == 0 => {
+                    self.advance();
+                    long_count = 1;
+                    if self.peek() == Some(l) {
                         self.advance();
+                        long_count = 2;
                     }
-                    if l_count == 1 || l_count == 2 {
-                        long_count += l_count as u8;
-                    } else {
-                        return Some(NumberSuffix::Integer(IntegerSuffix::new(unsigned, long_count)));
+                }
+                // C23 bit-precise 'wb' or 'WB', never mixed case
+                Some(w @ ('w' | 'W')) if self.standard >= Standard::C23 && !bit_precise && long_count == 0 => {
+                    let b = if w == 'w' { b'b' } else { b'B' };
+                    if self.peek_next_byte() != Some(b) {
+                        break;
                     }
+                    self.advance(); // Consume 'w'
+                    self.advance(); // Consume 'b'
+                    bit_precise = true;
                 }
                 _ => break,
             }
         }
 
-        if unsigned || long_count > 0 {
+        if bit_precise {
+            Some(NumberSuffix::Integer(IntegerSuffix::bit_precise(unsigned)))
+        } else if unsigned || long_count > 0 {
             Some(NumberSuffix::Integer(IntegerSuffix::new(unsigned, long_count)))
         } else {
             None
@@ -245,130 +530,249 @@ impl Lexer {
         }
     }
 
-    fn parse_block_comment(&mut self) -> Token {
-        let mut comment = String::new();
+    fn parse_block_comment(&mut self) -> Token<&'src str> {
+        let start = self.position;
 
-        let mut closed = false;
         while let Some(c) = self.peek() {
-            if c == '*' && self.input.get(self.position + 1) == Some(&'/') {
-                comment.push(c);
+            if c == '*' && self.peek_next_byte() == Some(b'/') {
+                let comment = self.slice(start).trim();
                 self.advance(); // Consume '*'
-                comment.push(self.advance().unwrap()); // Consume '/' and add to comment
-                closed = true;
-                break;
-            } else {
-                comment.push(c);
-                self.advance();
+                self.advance(); // Consume '/'
+                return Token::Comment(comment);
             }
+            self.advance();
         }
 
-        if closed {
-            comment.pop();
-            comment.pop();
-            comment = comment.trim().to_string();
-            Token::Comment(comment)
-        } else {
-            Token::Error("Unterminated block comment".to_string())
-        }
+        Token::Error("Unterminated block comment".to_string())
     }
 
-    fn parse_line_comment(&mut self) -> Token {
-        let mut comment = String::new();
-        while let Some(c) = self.peek() {
-            if c == '\n' {
-                break;
-            }
-            comment.push(c);
+    fn parse_line_comment(&mut self) -> Token<&'src str> {
+        let start = self.position;
+        while self.peek().is_some() && !self.at_line_end() {
             self.advance();
         }
         // trim leading and trailing whitespace
-        comment = comment.trim().to_string();
-        Token::Comment(comment)
+        Token::Comment(self.slice(start).trim())
     }
 
-    fn parse_char_literal(&mut self) -> Token {
-        self.advance(); // Consume opening quote
-        let char_lit = if let Some(c) = self.advance() {
-            c
-        } else {
-            return Token::Error("Unterminated character literal".to_string());
+    // Decode the escape sequence after a backslash
+    fn parse_escape(&mut self) -> Result<Escaped, String> {
+        let c = match self.advance() {
+            Some(c) => c,
+            None => return Err("Unterminated escape sequence".to_string()),
         };
 
-        if self.peek() == Some('\'') {
-            self.advance(); // Consume closing quote
-            Token::CharLiteral(char_lit.to_string())
-        } else {
-            Token::Error("Unclosed character literal".to_string())
-        }
+        let byte = match c {
+            '\'' => b'\'',
+            '"' => b'"',
+            '?' => b'?',
+            '\\' => b'\\',
+            'a' => 0x07,
+            'b' => 0x08,
+            'f' => 0x0c,
+            'n' => b'\n',
+            'r' => b'\r',
+            't' => b'\t',
+            'v' => 0x0b,
+            // \ooo, at most three octal digits
+            '0'..='7' => {
+                let mut value = c.to_digit(8).unwrap();
+                for _ in 0..2 {
+                    match self.peek().and_then(|c| c.to_digit(8)) {
+                        Some(digit) => {
+                            value = value * 8 + digit;
+                            self.advance();
+                        }
+                        None => break,
+                    }
+                }
+                return u8::try_from(value)
+                    .map(Escaped::Byte)
+                    .map_err(|_| "Octal escape sequence out of range".to_string());
+            }
+            // \xhh..., as many hex digits as follow
+            'x' => {
+                let mut value: u32 = 0;
+                let mut digits = 0;
+                while let Some(digit) = self.peek().and_then(|c| c.to_digit(16)) {
+                    value = value.saturating_mul(16).saturating_add(digit);
+                    digits += 1;
+                    self.advance();
+                }
+                if digits == 0 {
+                    return Err("\\x used with no following hex digits".to_string());
+                }
+                return u8::try_from(value)
+                    .map(Escaped::Byte)
+                    .map_err(|_| "Hex escape sequence out of range".to_string());
+            }
+            // \uXXXX and \UXXXXXXXX universal character names
+            'u' | 'U' => {
+                let len = if c == 'u' { 4 } else { 8 };
+                let mut value: u32 = 0;
+                for _ in 0..len {
+                    match self.peek().and_then(|c| c.to_digit(16)) {
+                        Some(digit) => {
+                            value = value * 16 + digit;
+                            self.advance();
+                        }
+                        None => return Err(format!("Incomplete universal character name \\{}", c)),
+                    }
+                }
+                // Only $, @ and ` may be spelled as a UCN below U+00A0
+                let allowed = value >= 0xA0 || matches!(value, 0x24 | 0x40 | 0x60);
+                return match char::from_u32(value) {
+                    Some(ch) if allowed => Ok(Escaped::Char(ch)),
+                    _ => Err(format!("Invalid universal character \\{}{:0len$X}", c, value, len = len)),
+                };
+            }
+            _ => return Err(format!("Unknown escape sequence '\\{}'", c)),
+        };
+
+        Ok(Escaped::Byte(byte))
     }
 
-    fn parse_string_literal(&mut self) -> Token {
+    fn parse_char_literal(&mut self) -> Token<&'src str> {
         self.advance(); // Consume opening quote
-        let mut string_lit = String::new();
-        while let Some(c) = self.peek() {
-            if c == '"' {
-                self.advance(); // Consume closing quote
-                break;
+        let start = self.position;
+        let mut chars: Vec<u32> = Vec::new();
+        let mut error = None;
+
+        loop {
+            match self.peek() {
+                None => return Token::Error("Unclosed character literal".to_string()),
+                Some(_) if self.at_line_end() => return Token::Error("Unclosed character literal".to_string()),
+                Some('\'') => break,
+                Some('\\') => {
+                    self.advance();
+                    match self.parse_escape() {
+                        Ok(Escaped::Byte(b)) => chars.push(b as u32),
+                        Ok(Escaped::Char(c)) => chars.push(c as u32),
+                        Err(e) => error = error.or(Some(e)),
+                    }
+                }
+                Some(c) => {
+                    self.advance();
+                    chars.push(c as u32);
+                }
             }
-            string_lit.push(c);
-            self.advance();
         }
-        Token::StringLiteral(string_lit)
+        let raw = self.slice(start);
+        self.advance(); // Consume closing quote
+
+        if let Some(e) = error {
+            return Token::Error(e);
+        }
+        if chars.is_empty() {
+            return Token::Error("Empty character constant".to_string());
+        }
+
+        // Multi-character constants like 'ab' pack one byte per char, as gcc does
+        let value = match chars.as_slice() {
+            [c] => *c,
+            _ => chars.iter().fold(0, |value, &c| (value << 8) | (c & 0xff)),
+        };
+        Token::CharLiteral { raw, value }
     }
 
-    fn parse_symbol(&mut self) -> Token {
-        let max_symbol_len = SYMBOLS.iter().map(|s| s.len()).max().unwrap_or(0);
-        let mut symbol = String::new();
-        let mut temp_position = self.position;
+    fn parse_string_literal(&mut self) -> Token<&'src str> {
+        self.advance(); // Consume opening quote
+        let start = self.position;
+        let mut value = Vec::new();
+        let mut error = None;
 
-        for _ in 0..max_symbol_len {
-            if let Some(c) = self.input.get(temp_position) {
-                symbol.push(*c);
-                temp_position += 1;
-            } else {
-                break;
+        while let Some(c) = self.peek() {
+            match c {
+                // A string cannot span lines; stop here so lexing resumes on
+                // the next line instead of swallowing the rest of the file
+                _ if self.at_line_end() => break,
+                '"' => {
+                    let raw = self.slice(start);
+                    self.advance(); // Consume closing quote
+                    return match error {
+                        Some(e) => Token::Error(e),
+                        None => Token::StringLiteral { raw, value },
+                    };
+                }
+                '\\' => {
+                    self.advance();
+                    match self.parse_escape() {
+                        Ok(Escaped::Byte(b)) => value.push(b),
+                        Ok(Escaped::Char(c)) => value.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes()),
+                        Err(e) => error = error.or(Some(e)),
+                    }
+                }
+                _ => {
+                    self.advance();
+                    value.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes());
+                }
             }
         }
+        Token::Error("Unterminated string literal".to_string())
+    }
 
-        for i in (1..=symbol.len()).rev() {
-            let candidate = &symbol[..i];
-            if SYMBOLS.contains(&candidate) {
-                for _ in 0..i {
-                    self.advance();
+    // The input from the current char on, bytes of chars as `peek` sees them
+    fn logical_bytes(&self) -> impl Iterator<Item = u8> + '_ {
+        let mut offset = self.position;
+        std::iter::from_fn(move || {
+            offset = self.skip_splices_from(offset);
+            let (c, len) = self.char_at(offset)?;
+            offset += len;
+            let mut buf = [0; 4];
+            let n = c.encode_utf8(&mut buf).len();
+            Some(buf.into_iter().take(n))
+        })
+        .flatten()
+    }
+
+    fn parse_symbol(&mut self) -> Token<&'src str> {
+        let trie = self.config.trie();
+        let rest = &self.input.as_bytes()[self.position..];
+        // Match the input as it is unless a splice or trigraph may be in the way
+        let plain = rest.iter().take(trie.max_len()).all(|&b| b != b'\\' && !(self.trigraphs && b == b'?'));
+        let found = if plain {
+            trie.longest_match(rest.iter().copied())
+        } else {
+            trie.longest_match(self.logical_bytes())
+        };
+        match found {
+            Some((punct, len)) => {
+                let mut consumed = 0;
+                while consumed < len {
+                    consumed += self.advance().map_or(len, char::len_utf8);
                 }
-                return Token::Symbol(candidate.to_string());
+                Token::Symbol(punct)
             }
+            None => Token::Error(format!("Unknown symbol starting with '{}'", self.advance().unwrap())),
         }
-
-        Token::Error(format!("Unknown symbol starting with '{}'", self.advance().unwrap()))
     }
 
-    pub fn next_token(&mut self) -> Option<PositionedToken> {
+    pub fn next_token(&mut self) -> Option<PositionedToken<&'src str>> {
         self.skip_whitespace();
+        self.skip_splices();
 
-        let start_row = self.row;
-        let start_column = self.column;
+        let start = self.current_position();
 
         let token = match self.peek()? {
             c if c.is_alphabetic() || c == '_' => self.parse_identifier_or_keyword(),
-            c if c.is_digit(10) => self.parse_number(),
+            c if c.is_ascii_digit() => self.parse_number(),
+            '.' if self.peek_next_byte().is_some_and(|b| b.is_ascii_digit()) => self.parse_number(),
             '/' => {
-                if self.input.get(self.position + 1) == Some(&'*') {
+                if self.peek_next_byte() == Some(b'*') {
                     self.advance(); // Consume '/'
                     self.advance(); // Consume '*'
                     self.parse_block_comment()
-                } else if self.input.get(self.position + 1) == Some(&'/') {
+                } else if self.peek_next_byte() == Some(b'/') {
                     self.advance(); // Consume '/'
                     self.advance(); // Consume '/'
                     self.parse_line_comment()
                 } else {
-                    self.advance(); // Consume '/'
-                    Token::Symbol("/".to_string())
+                    self.parse_symbol()
                 }
             },
             '\'' => self.parse_char_literal(),
             '"' => self.parse_string_literal(),
-            c if SYMBOLS.iter().any(|s| s.starts_with(c)) => self.parse_symbol(),
+            c if self.config.trie().starts_with(c.encode_utf8(&mut [0; 4]).as_bytes()[0]) => self.parse_symbol(),
             _ => {
                 let unexpected_char = self.advance().unwrap();
                 Token::Error(format!("Unexpected character: {}", unexpected_char))
@@ -377,8 +781,7 @@ impl Lexer {
 
         Some(PositionedToken {
             token,
-            row: start_row,
-            column: start_column,
+            span: Span::new(start, self.current_position()),
         })
     }    
 
@@ -389,15 +792,15 @@ impl Lexer {
             tokens: Vec::new(),
         };
 
-        while let Some(positioned_token) = self.next_token() {
+        for positioned_token in self.by_ref().map(PositionedToken::into_owned) {
             // If the token is on a new line, create a new TokenLine
-            if positioned_token.row != current_line.line_number {
+            if positioned_token.span.start.row != current_line.line_number {
                 // Only add non-empty token lines
                 if !current_line.tokens.is_empty() {
                     token_lines.push(current_line);
                 }
                 current_line = TokenLine {
-                    line_number: positioned_token.row,
+                    line_number: positioned_token.span.start.row,
                     tokens: vec![positioned_token],
                 };
             } else {
@@ -413,14 +816,23 @@ impl Lexer {
         token_lines
     }
 
-    // Just parse as tokens    
+    // Just parse as tokens, owning their text
     pub fn tokenize(&mut self) -> Vec<PositionedToken> {
-        let mut tokens: Vec<PositionedToken> = Vec::new();
+        self.by_ref().map(PositionedToken::into_owned).collect()
+    }
 
-        while let Some(positioned_token) = self.next_token() {
-            tokens.push(positioned_token);
-        }
+    /// Keep whitespace and comments as trivia of the tokens, so that the
+    /// input can be rebuilt from them.
+    pub fn lossless(self) -> Lossless<'src> {
+        let (input, start) = (self.input, self.current_position());
+        Lossless::new(input, self, start)
+    }
+}
+
+impl<'src> Iterator for Lexer<'src> {
+    type Item = PositionedToken<&'src str>;
 
-        tokens
+    fn next(&mut self) -> Option<PositionedToken<&'src str>> {
+        self.next_token()
     }
 }
@@ -1,7 +1,29 @@
+pub mod ast;
+pub mod cst;
+pub mod diagnostics;
+pub mod number;
 mod token;
 mod lexer;
 mod parser;
+mod operators;
+mod source;
+mod trie;
+mod error;
+mod config;
+mod trivia;
+mod incremental;
+mod preprocessor;
 
-pub use token::{Token, TokenLine};
+pub use token::{
+    FloatingPointSuffix, IntegerSuffix, NumberBase, NumberKind, NumberSuffix, Position,
+    Keyword, PositionedToken, Punct, Span, Token, TokenKind, TokenLine, TokenRef, DIGRAPHS, KEYWORDS, SYMBOLS,
+};
 pub use lexer::Lexer;
+pub use trivia::{Lossless, LosslessToken, Trivia, TriviaKind};
+pub use config::{LanguageProfile, LexerConfig, Standard};
 pub use parser::Parser;
+pub use operators::{Assoc, Infix, InfixOp, OperatorTable, Precedence};
+pub use incremental::{Document, TextEdit};
+pub use preprocessor::{ExpandedToken, FileId, Location, PreprocessError, Preprocessor, SourceFile};
+pub use source::TokenSource;
+pub use error::{Expected, ParseError, ParseErrorKind};
@@ -0,0 +1,296 @@
+use std::borrow::Cow;
+use std::fmt;
+
+use crate::lexer::translate;
+use crate::token::{FloatingPointSuffix, NumberBase, NumberKind, NumberSuffix, Token};
+
+// Integer types are sized as on LP64 targets: int is 32 bits, long and
+// long long are 64 bits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IntType {
+    Int,
+    UnsignedInt,
+    Long,
+    UnsignedLong,
+    LongLong,
+    UnsignedLongLong,
+    /// C23 `_BitInt(N)`, from a `wb` suffix.
+    BitInt(u16),
+    UnsignedBitInt(u16),
+}
+
+impl IntType {
+    pub fn max_value(&self) -> u64 {
+        match self {
+            IntType::Int => i32::MAX as u64,
+            IntType::UnsignedInt => u32::MAX as u64,
+            IntType::Long | IntType::LongLong => i64::MAX as u64,
+            IntType::UnsignedLong | IntType::UnsignedLongLong => u64::MAX,
+            IntType::BitInt(width) => u64::MAX >> (65 - (*width).clamp(2, 64)),
+            IntType::UnsignedBitInt(width) => u64::MAX >> (64 - (*width).clamp(1, 64)),
+        }
+    }
+
+    pub fn is_unsigned(&self) -> bool {
+        matches!(
+            self,
+            IntType::UnsignedInt | IntType::UnsignedLong | IntType::UnsignedLongLong | IntType::UnsignedBitInt(_)
+        )
+    }
+}
+
+impl fmt::Display for IntType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            IntType::BitInt(width) => return write!(f, "_BitInt({})", width),
+            IntType::UnsignedBitInt(width) => return write!(f, "unsigned _BitInt({})", width),
+            IntType::Int => "int",
+            IntType::UnsignedInt => "unsigned int",
+            IntType::Long => "long",
+            IntType::UnsignedLong => "unsigned long",
+            IntType::LongLong => "long long",
+            IntType::UnsignedLongLong => "unsigned long long",
+        })
+    }
+}
+
+// long double is evaluated with the precision of double
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FloatType {
+    Float,
+    Double,
+    LongDouble,
+}
+
+impl fmt::Display for FloatType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            FloatType::Float => "float",
+            FloatType::Double => "double",
+            FloatType::LongDouble => "long double",
+        })
+    }
+}
+
+/// The value of a numeric literal together with its C type.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Constant {
+    Integer { value: u64, ty: IntType },
+    Floating { value: f64, ty: FloatType },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NumberError {
+    /// A digit not allowed in the literal's base, like the 8 in `089`.
+    InvalidDigit { digit: char, base: NumberBase },
+    /// A prefix with nothing after it, like `0x`.
+    NoDigits { base: NumberBase },
+    /// `1e` or `1e+` without exponent digits.
+    MissingExponent,
+    /// A hexadecimal floating constant without its `p` exponent, like `0x1.8`.
+    MissingBinaryExponent,
+    /// A decimal floating constant that is not one otherwise, like `1.2.3`.
+    MalformedFloat,
+    /// The value does not fit in any type the literal may have.
+    IntegerOverflow,
+    /// The value is out of range for the floating type.
+    FloatOverflow { ty: FloatType },
+}
+
+impl fmt::Display for NumberError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NumberError::InvalidDigit { digit, base } => {
+                write!(f, "invalid digit '{}' in {} constant", digit, base_name(*base))
+            }
+            NumberError::NoDigits { base } => write!(f, "no digits in {} constant", base_name(*base)),
+            NumberError::MissingExponent => write!(f, "exponent has no digits"),
+            NumberError::MissingBinaryExponent => write!(f, "hexadecimal floating constant requires an exponent"),
+            NumberError::MalformedFloat => write!(f, "malformed floating constant"),
+            NumberError::IntegerOverflow => write!(f, "integer constant is too large for its type"),
+            NumberError::FloatOverflow { ty } => write!(f, "floating constant exceeds range of '{}'", ty),
+        }
+    }
+}
+
+impl std::error::Error for NumberError {}
+
+fn base_name(base: NumberBase) -> &'static str {
+    match base {
+        NumberBase::Binary => "binary",
+        NumberBase::Decimal => "decimal",
+        NumberBase::Octal => "octal",
+        NumberBase::Hexadecimal => "hexadecimal",
+    }
+}
+
+/// Compute the value and type of a numeric literal as split up by the lexer
+/// in `Token::Number`.
+pub fn evaluate(
+    literal: &str,
+    kind: NumberKind,
+    base: NumberBase,
+    suffix: Option<NumberSuffix>,
+) -> Result<Constant, NumberError> {
+    // Line splices and C23 digit separators carry no value
+    let literal = translate(literal, true);
+    let literal: Cow<str> = if literal.contains('\'') {
+        Cow::Owned(literal.replace('\'', ""))
+    } else {
+        literal
+    };
+    match kind {
+        NumberKind::Integer => evaluate_integer(&literal, base, suffix),
+        NumberKind::FloatingPoint if base == NumberBase::Hexadecimal => evaluate_hex_float(&literal, suffix),
+        NumberKind::FloatingPoint => evaluate_float(&literal, suffix),
+    }
+}
+
+impl<S: AsRef<str>> Token<S> {
+    /// The value of a number token, `None` for any other token.
+    pub fn number_value(&self) -> Option<Result<Constant, NumberError>> {
+        match self {
+            Token::Number { literal, kind, base, suffix, .. } => Some(evaluate(literal.as_ref(), *kind, *base, *suffix)),
+            _ => None,
+        }
+    }
+}
+
+fn evaluate_integer(literal: &str, base: NumberBase, suffix: Option<NumberSuffix>) -> Result<Constant, NumberError> {
+    let (digits, radix) = match base {
+        NumberBase::Binary => (&literal[2..], 2),
+        NumberBase::Hexadecimal => (&literal[2..], 16),
+        NumberBase::Octal => (&literal[1..], 8),
+        NumberBase::Decimal => (literal, 10),
+    };
+    if digits.is_empty() {
+        return Err(NumberError::NoDigits { base });
+    }
+
+    let mut value: u64 = 0;
+    let mut overflow = false;
+    for digit in digits.chars() {
+        let d = digit
+            .to_digit(radix)
+            .ok_or(NumberError::InvalidDigit { digit, base })?;
+        match value.checked_mul(radix as u64).and_then(|v| v.checked_add(d as u64)) {
+            Some(v) => value = v,
+            // Keep scanning, an invalid digit is the better error
+            None => overflow = true,
+        }
+    }
+    if overflow {
+        return Err(NumberError::IntegerOverflow);
+    }
+
+    let (unsigned, long) = match suffix {
+        Some(NumberSuffix::Integer(suffix)) if suffix.is_bit_precise() => {
+            // C23 6.4.4.1: the narrowest _BitInt that holds the value, at
+            // least two bits wide when signed
+            let bits = (u64::BITS - value.leading_zeros()) as u16;
+            let ty = if suffix.is_unsigned() {
+                IntType::UnsignedBitInt(bits.max(1))
+            } else {
+                IntType::BitInt((bits + 1).max(2))
+            };
+            return Ok(Constant::Integer { value, ty });
+        }
+        Some(NumberSuffix::Integer(suffix)) => (suffix.is_unsigned(), suffix.long()),
+        _ => (false, 0),
+    };
+
+    // C17 6.4.4.1: the first type in the list that can represent the value
+    use IntType::*;
+    let decimal = base == NumberBase::Decimal;
+    let candidates: &[IntType] = match (unsigned, long, decimal) {
+        (false, 0, true) => &[Int, Long, LongLong],
+        (false, 0, false) => &[Int, UnsignedInt, Long, UnsignedLong, LongLong, UnsignedLongLong],
+        (true, 0, _) => &[UnsignedInt, UnsignedLong, UnsignedLongLong],
+        (false, 1, true) => &[Long, LongLong],
+        (false, 1, false) => &[Long, UnsignedLong, LongLong, UnsignedLongLong],
+        (true, 1, _) => &[UnsignedLong, UnsignedLongLong],
+        (false, _, true) => &[LongLong],
+        (false, _, false) => &[LongLong, UnsignedLongLong],
+        (true, _, _) => &[UnsignedLongLong],
+    };
+
+    candidates
+        .iter()
+        .find(|ty| value <= ty.max_value())
+        .map(|&ty| Constant::Integer { value, ty })
+        .ok_or(NumberError::IntegerOverflow)
+}
+
+fn float_type(suffix: Option<NumberSuffix>) -> FloatType {
+    match suffix {
+        Some(NumberSuffix::FloatingPoint(FloatingPointSuffix::Float)) => FloatType::Float,
+        Some(NumberSuffix::FloatingPoint(FloatingPointSuffix::LongDouble)) => FloatType::LongDouble,
+        _ => FloatType::Double,
+    }
+}
+
+fn evaluate_float(literal: &str, suffix: Option<NumberSuffix>) -> Result<Constant, NumberError> {
+    if literal.ends_with(['e', 'E', '+', '-']) {
+        return Err(NumberError::MissingExponent);
+    }
+    let value: f64 = literal.parse().map_err(|_| NumberError::MalformedFloat)?;
+    float_constant(value, float_type(suffix))
+}
+
+// 0x<hex digits>[.<hex digits>]p[+-]<decimal digits>, value is the mantissa
+// times two to the exponent
+fn evaluate_hex_float(literal: &str, suffix: Option<NumberSuffix>) -> Result<Constant, NumberError> {
+    let base = NumberBase::Hexadecimal;
+    let (mantissa, exponent) = match literal[2..].split_once(['p', 'P']) {
+        Some(parts) => parts,
+        None => return Err(NumberError::MissingBinaryExponent),
+    };
+    let (whole, fraction) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+    if whole.is_empty() && fraction.is_empty() {
+        return Err(NumberError::NoDigits { base });
+    }
+
+    let exponent = exponent.strip_prefix('+').unwrap_or(exponent);
+    if exponent.is_empty() || exponent == "-" {
+        return Err(NumberError::MissingExponent);
+    }
+    // An absurd exponent saturates to infinity or zero either way
+    let mut exponent: i64 = exponent.parse().unwrap_or(if exponent.starts_with('-') { -100_000 } else { 100_000 });
+
+    // Keep the leading 60 bits of the mantissa, the rest only shift the value
+    let mut value: u64 = 0;
+    for (i, digit) in whole.chars().chain(fraction.chars()).enumerate() {
+        let d = digit.to_digit(16).ok_or(NumberError::InvalidDigit { digit, base })?;
+        if value >> 56 == 0 {
+            value = value * 16 + d as u64;
+            if i >= whole.len() {
+                exponent -= 4;
+            }
+        } else if i < whole.len() {
+            exponent += 4;
+        }
+    }
+
+    // Scale in two steps so a large mantissa with a tiny exponent does not
+    // underflow halfway
+    let exponent = exponent.clamp(-4000, 4000) as i32;
+    let half = 2f64.powi(exponent / 2);
+    let value = value as f64 * half * half * 2f64.powi(exponent % 2);
+    float_constant(value, float_type(suffix))
+}
+
+fn float_constant(value: f64, ty: FloatType) -> Result<Constant, NumberError> {
+    let in_range = match ty {
+        FloatType::Float => value.is_finite() && value.abs() <= f32::MAX as f64,
+        FloatType::Double | FloatType::LongDouble => value.is_finite(),
+    };
+    if !in_range {
+        return Err(NumberError::FloatOverflow { ty });
+    }
+
+    let value = match ty {
+        FloatType::Float => value as f32 as f64,
+        FloatType::Double | FloatType::LongDouble => value,
+    };
+    Ok(Constant::Floating { value, ty })
+}
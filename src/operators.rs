@@ -0,0 +1,189 @@
+use crate::ast::{BinOp, LogicalOp, UnaryOp};
+use crate::config::LanguageProfile;
+use crate::token::Punct;
+
+/// The precedence levels of C (C17 6.5), loosest first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Precedence {
+    Comma,          // ,
+    Assignment,     // = *= /= %= += -= <<= >>= &= ^= |=
+    Conditional,    // ?:
+    LogicalOr,      // ||
+    LogicalAnd,     // &&
+    BitOr,          // |
+    BitXor,         // ^
+    BitAnd,         // &
+    Equality,       // == !=
+    Relational,     // < > <= >=
+    Shift,          // << >>
+    Additive,       // + -
+    Multiplicative, // * / %
+    Unary,          // prefix operators, casts, sizeof
+    Postfix,        // [] . -> ++ --
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Assoc {
+    Left,
+    Right,
+}
+
+/// What an infix operator builds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InfixOp {
+    Binary(BinOp),
+    Logical(LogicalOp),
+    // `=` is `Assign(None)`, `+=` is `Assign(Some(BinOp::Add))`
+    Assign(Option<BinOp>),
+    // ? expr : with the middle operand parsed like one in parentheses
+    Conditional,
+    Comma,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Infix {
+    pub op: InfixOp,
+    pub precedence: Precedence,
+    pub assoc: Assoc,
+}
+
+/// The operators the parser knows, by punctuator. `[]`, `.`, `->`, casts
+/// and `sizeof` are part of the grammar; everything else comes from the
+/// table, so a profile can add operators of its own:
+///
+/// ```
+/// use lexer_and_parser::{Assoc, LanguageProfile, Lexer, LexerConfig, OperatorTable, Parser, Precedence};
+///
+/// let config = LexerConfig::new(LanguageProfile::C17).with_punct("**");
+/// let operators = OperatorTable::new(LanguageProfile::C17).with_binary("**", Precedence::Unary, Assoc::Right);
+/// let lexer = Lexer::new_with_config("{ x = -2 ** 3 ** 2; }", &config);
+/// let (_, errors) = Parser::new(lexer).with_operators(operators).parse_program();
+/// assert!(errors.is_empty());
+/// ```
+#[derive(Debug, Clone)]
+pub struct OperatorTable {
+    prefix: Vec<(Punct, UnaryOp)>,
+    postfix: Vec<(Punct, UnaryOp)>,
+    infix: Vec<(Punct, Infix)>,
+}
+
+impl OperatorTable {
+    /// The C operators whose punctuators the profile has.
+    pub fn new(profile: LanguageProfile) -> Self {
+        let puncts = profile.puncts();
+        let has = |p: &Punct| puncts.contains(p);
+        OperatorTable {
+            prefix: PREFIX.iter().filter(|(p, _)| has(p)).copied().collect(),
+            postfix: POSTFIX.iter().filter(|(p, _)| has(p)).copied().collect(),
+            infix: INFIX
+                .iter()
+                .filter(|(p, _, _)| has(p))
+                .map(|&(p, op, precedence)| (p, Infix { op, precedence, assoc: c_assoc(precedence) }))
+                .collect(),
+        }
+    }
+
+    /// Parse `spelling` as a binary operator at `precedence`. A C operator
+    /// keeps what it builds and only moves; any other spelling builds
+    /// `BinOp::Extension`. The lexer needs the spelling too, see
+    /// `LexerConfig::with_punct`.
+    pub fn with_binary(mut self, spelling: &'static str, precedence: Precedence, assoc: Assoc) -> Self {
+        let punct = Punct::from_spelling(spelling).unwrap_or(Punct::Extension(spelling));
+        match self.infix.iter_mut().find(|(p, _)| *p == punct) {
+            Some((_, infix)) => {
+                infix.precedence = precedence;
+                infix.assoc = assoc;
+            }
+            None => {
+                let op = InfixOp::Binary(BinOp::Extension(spelling));
+                self.infix.push((punct, Infix { op, precedence, assoc }));
+            }
+        }
+        self
+    }
+
+    /// Parse `spelling` as a prefix operator building `UnaryOp::Extension`.
+    pub fn with_prefix(mut self, spelling: &'static str) -> Self {
+        let punct = Punct::from_spelling(spelling).unwrap_or(Punct::Extension(spelling));
+        if self.prefix(punct).is_none() {
+            self.prefix.push((punct, UnaryOp::Extension(spelling)));
+        }
+        self
+    }
+
+    pub fn prefix(&self, punct: Punct) -> Option<UnaryOp> {
+        self.prefix.iter().find(|(p, _)| *p == punct).map(|(_, op)| *op)
+    }
+
+    pub fn postfix(&self, punct: Punct) -> Option<UnaryOp> {
+        self.postfix.iter().find(|(p, _)| *p == punct).map(|(_, op)| *op)
+    }
+
+    pub fn infix(&self, punct: Punct) -> Option<Infix> {
+        self.infix.iter().find(|(p, _)| *p == punct).map(|(_, infix)| *infix)
+    }
+}
+
+impl Default for OperatorTable {
+    fn default() -> Self {
+        OperatorTable::new(LanguageProfile::C17)
+    }
+}
+
+// Only the assignments and the conditional operator group to the right
+fn c_assoc(precedence: Precedence) -> Assoc {
+    match precedence {
+        Precedence::Assignment | Precedence::Conditional => Assoc::Right,
+        _ => Assoc::Left,
+    }
+}
+
+const PREFIX: &[(Punct, UnaryOp)] = &[
+    (Punct::PlusPlus,   UnaryOp::PreInc),
+    (Punct::MinusMinus, UnaryOp::PreDec),
+    (Punct::Amp,        UnaryOp::AddrOf),
+    (Punct::Star,       UnaryOp::Deref),
+    (Punct::Plus,       UnaryOp::Plus),
+    (Punct::Minus,      UnaryOp::Neg),
+    (Punct::Tilde,      UnaryOp::BitNot),
+    (Punct::Bang,       UnaryOp::Not),
+];
+
+const POSTFIX: &[(Punct, UnaryOp)] = &[
+    (Punct::PlusPlus,   UnaryOp::PostInc),
+    (Punct::MinusMinus, UnaryOp::PostDec),
+];
+
+const INFIX: &[(Punct, InfixOp, Precedence)] = &[
+    (Punct::Star,      InfixOp::Binary(BinOp::Mul),          Precedence::Multiplicative),
+    (Punct::Slash,     InfixOp::Binary(BinOp::Div),          Precedence::Multiplicative),
+    (Punct::Percent,   InfixOp::Binary(BinOp::Rem),          Precedence::Multiplicative),
+    (Punct::Plus,      InfixOp::Binary(BinOp::Add),          Precedence::Additive),
+    (Punct::Minus,     InfixOp::Binary(BinOp::Sub),          Precedence::Additive),
+    (Punct::Shl,       InfixOp::Binary(BinOp::Shl),          Precedence::Shift),
+    (Punct::Shr,       InfixOp::Binary(BinOp::Shr),          Precedence::Shift),
+    (Punct::Lt,        InfixOp::Binary(BinOp::Lt),           Precedence::Relational),
+    (Punct::Gt,        InfixOp::Binary(BinOp::Gt),           Precedence::Relational),
+    (Punct::Le,        InfixOp::Binary(BinOp::Le),           Precedence::Relational),
+    (Punct::Ge,        InfixOp::Binary(BinOp::Ge),           Precedence::Relational),
+    (Punct::EqEq,      InfixOp::Binary(BinOp::Eq),           Precedence::Equality),
+    (Punct::Ne,        InfixOp::Binary(BinOp::Ne),           Precedence::Equality),
+    (Punct::Amp,       InfixOp::Binary(BinOp::BitAnd),       Precedence::BitAnd),
+    (Punct::Caret,     InfixOp::Binary(BinOp::BitXor),       Precedence::BitXor),
+    (Punct::Pipe,      InfixOp::Binary(BinOp::BitOr),        Precedence::BitOr),
+    (Punct::AmpAmp,    InfixOp::Logical(LogicalOp::And),     Precedence::LogicalAnd),
+    (Punct::PipePipe,  InfixOp::Logical(LogicalOp::Or),      Precedence::LogicalOr),
+    (Punct::Question,  InfixOp::Conditional,                 Precedence::Conditional),
+    (Punct::Eq,        InfixOp::Assign(None),                Precedence::Assignment),
+    (Punct::StarEq,    InfixOp::Assign(Some(BinOp::Mul)),    Precedence::Assignment),
+    (Punct::SlashEq,   InfixOp::Assign(Some(BinOp::Div)),    Precedence::Assignment),
+    (Punct::PercentEq, InfixOp::Assign(Some(BinOp::Rem)),    Precedence::Assignment),
+    (Punct::PlusEq,    InfixOp::Assign(Some(BinOp::Add)),    Precedence::Assignment),
+    (Punct::MinusEq,   InfixOp::Assign(Some(BinOp::Sub)),    Precedence::Assignment),
+    (Punct::ShlEq,     InfixOp::Assign(Some(BinOp::Shl)),    Precedence::Assignment),
+    (Punct::ShrEq,     InfixOp::Assign(Some(BinOp::Shr)),    Precedence::Assignment),
+    (Punct::AmpEq,     InfixOp::Assign(Some(BinOp::BitAnd)), Precedence::Assignment),
+    (Punct::CaretEq,   InfixOp::Assign(Some(BinOp::BitXor)), Precedence::Assignment),
+    (Punct::PipeEq,    InfixOp::Assign(Some(BinOp::BitOr)),  Precedence::Assignment),
+    (Punct::Comma,     InfixOp::Comma,                       Precedence::Comma),
+];
@@ -1,198 +1,1325 @@
-use crate::lexer::Lexer;
-use crate::token::{PositionedToken, Token};
+use std::collections::HashMap;
 
-pub struct Parser {
-    tokens: Vec<PositionedToken>,
-    pos: usize,
+use crate::ast::*;
+use crate::cst::{SyntaxKind, TreeBuilder};
+use crate::error::{Expected, ParseError, ParseErrorKind, FIRST_EXPR, FIRST_ITEM, FIRST_STMT, TYPE_KEYWORDS};
+use crate::incremental::{Cached, Names, ParsedStmt, StmtCache};
+use crate::lexer::translate;
+use crate::operators::{Assoc, Infix, InfixOp, OperatorTable, Precedence};
+use crate::source::TokenSource;
+use crate::token::{Keyword, Position, PositionedToken, Punct, Token};
+
+pub struct Parser<S, I: Iterator<Item = PositionedToken<S>>> {
+    tokens: TokenSource<S, I>,
+    // Every token tried at the current position, reported when nothing matches
+    expected: Vec<Expected>,
+    errors: Vec<ParseError>,
+    // Shape of the concrete syntax tree, when one is wanted
+    tree: Option<TreeBuilder>,
+    // Top-level items and statements to reuse and record, when parsing
+    // incrementally
+    cache: Option<StmtCache>,
+    operators: OperatorTable,
+    // Ordinary identifiers by scope, innermost last, and whether each is a
+    // typedef name
+    scopes: Vec<HashMap<String, bool>>,
+    // The tokens come from a lexer replacing trigraphs
+    trigraphs: bool,
+    // The names the top-level item or statement being cached depends on
+    names: Option<Names>,
+}
+
+// Whether a declarator has to, may or must not name something
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DeclaratorKind {
+    Named,
+    Abstract,
+    Either,
 }
 
-impl Parser {
-    pub fn new(mut lexer: Lexer) -> Self {
-        let tokens = lexer.tokenize();
-        Self { tokens, pos: 0 }
+impl<S, I> Parser<S, I>
+where
+    S: AsRef<str> + Clone,
+    I: Iterator<Item = PositionedToken<S>>,
+{
+    /// Create a parser pulling tokens lazily from `tokens`, usually a `Lexer`.
+    pub fn new(tokens: impl IntoIterator<IntoIter = I>) -> Self {
+        Self {
+            tokens: TokenSource::new(tokens),
+            expected: Vec::new(),
+            errors: Vec::new(),
+            tree: None,
+            cache: None,
+            operators: OperatorTable::default(),
+            scopes: vec![HashMap::new()],
+            trigraphs: false,
+            names: None,
+        }
+    }
+
+    /// Keep skipped comments for `comments` and `take_comments`, the default,
+    /// or drop them as they are skipped.
+    pub fn with_comments(mut self, keep: bool) -> Self {
+        self.tokens = self.tokens.with_comments(keep);
+        self
+    }
+
+    /// Read names as a lexer with `LexerConfig::with_trigraphs(trigraphs)`
+    /// spells them, so that `??/` line splices in them are removed too.
+    pub fn with_trigraphs(mut self, trigraphs: bool) -> Self {
+        self.trigraphs = trigraphs;
+        self
+    }
+
+    /// Parse expressions with `operators` instead of the C operators.
+    pub fn with_operators(mut self, operators: OperatorTable) -> Self {
+        self.operators = operators;
+        self
+    }
+
+    // Record the concrete syntax tree while parsing
+    pub(crate) fn with_tree(mut self) -> Self {
+        self.tree = Some(TreeBuilder::default());
+        self
+    }
+
+    pub(crate) fn take_tree(&mut self) -> Option<TreeBuilder> {
+        self.tree.take()
+    }
+
+    pub(crate) fn with_cache(mut self, cache: StmtCache) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    pub(crate) fn take_cache(&mut self) -> Option<StmtCache> {
+        self.cache.take()
+    }
+
+    /// Comments the parser has skipped over and not taken yet, in source
+    /// order.
+    pub fn comments(&self) -> &[PositionedToken<S>] {
+        self.tokens.comments()
+    }
+
+    /// Take the comments skipped over so far, so that they do not pile up
+    /// while a long input is parsed.
+    pub fn take_comments(&mut self) -> Vec<PositionedToken<S>> {
+        self.tokens.take_comments()
     }
 
-    fn current_token(&self) -> &PositionedToken {
-        &self.tokens[self.pos]
+    fn current_token(&self) -> &PositionedToken<S> {
+        self.tokens.peek()
+    }
+
+    fn at_end(&self) -> bool {
+        self.tokens.at_end()
     }
 
     fn advance(&mut self) {
-        if self.pos < self.tokens.len() - 1 {
-            self.pos += 1;
+        if !self.at_end() {
+            if let Some(tree) = &mut self.tree {
+                tree.token();
+            }
         }
+        self.tokens.bump();
+        self.expected.clear();
     }
 
-    fn expect(&mut self, expected: &Token) -> Result<(), String> {
-        if self.current_token().token != *expected {
-            return Err(format!(
-                "Expected token {:?}, found {:?}",
-                expected, self.current_token(),
-            ));
+    fn start_node(&mut self, kind: SyntaxKind) {
+        if let Some(tree) = &mut self.tree {
+            tree.start_node(kind);
         }
-        self.advance();
-        Ok(())
     }
 
-    pub fn parse_program(&mut self) -> Result<(), String> {
-        self.parse_block()
+    fn start_node_at(&mut self, checkpoint: usize, kind: SyntaxKind) {
+        if let Some(tree) = &mut self.tree {
+            tree.start_node_at(checkpoint, kind);
+        }
+    }
+
+    fn finish_node(&mut self) {
+        if let Some(tree) = &mut self.tree {
+            tree.finish_node();
+        }
+    }
+
+    fn checkpoint(&self) -> usize {
+        self.tree.as_ref().map_or(0, TreeBuilder::checkpoint)
+    }
+
+    fn depth(&self) -> usize {
+        self.tree.as_ref().map_or(0, TreeBuilder::depth)
+    }
+
+    // After an error: close the nodes it left open, down to `depth`, then
+    // wrap everything from `checkpoint` on in an error node
+    fn recover_tree(&mut self, checkpoint: usize, depth: usize) {
+        if let Some(tree) = &mut self.tree {
+            tree.finish_to(depth);
+            tree.start_node_at(checkpoint, SyntaxKind::Error);
+        }
+    }
+
+    // Test the current token and remember that it would have been accepted
+    fn check(&mut self, expected: Expected) -> bool {
+        if !self.expected.contains(&expected) {
+            self.expected.push(expected);
+        }
+        expected.matches(&self.current_token().token)
+    }
+
+    fn check_any(&mut self, expected: &[Expected]) {
+        for e in expected {
+            self.check(*e);
+        }
+    }
+
+    fn eat(&mut self, expected: Expected) -> bool {
+        if self.check(expected) {
+            self.advance();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn error(&mut self, kind: ParseErrorKind) -> ParseError {
+        let expected = std::mem::take(&mut self.expected);
+        let current = self.current_token();
+        let kind = match current.token {
+            Token::Error(_) => ParseErrorKind::InvalidToken,
+            Token::Eof => ParseErrorKind::UnexpectedEof,
+            _ => kind,
+        };
+        ParseError {
+            kind,
+            span: current.span,
+            expected,
+            found: Box::new(current.token.clone().into_owned()),
+        }
+    }
+
+    // Keep the first error reported at a position; later ones are cascades
+    fn record(&mut self, err: ParseError) {
+        if self.errors.last().map(|last| last.span) != Some(err.span) {
+            self.errors.push(err);
+        }
+    }
+
+    fn lookup(&self, name: &str) -> bool {
+        self.scopes.iter().rev().find_map(|scope| scope.get(name).copied()).unwrap_or(false)
+    }
+
+    // Whether `name` is a typedef name here, remembered for the cache
+    fn is_typedef(&mut self, name: &str) -> bool {
+        let typedef = self.lookup(name);
+        if let Some(names) = &mut self.names {
+            names.lookups.push((name.to_string(), typedef));
+        }
+        typedef
+    }
+
+    fn declare(&mut self, name: &str, typedef: bool) {
+        let depth = self.scopes.len();
+        self.scopes.last_mut().expect("file scope").insert(name.to_string(), typedef);
+        if let Some(names) = self.names.as_mut().filter(|names| names.depth == depth) {
+            names.declared.push((name.to_string(), typedef));
+        }
     }
 
-    fn parse_block(&mut self) -> Result<(), String> {
-        self.expect(&Token::Symbol("{".to_string()))?;
-        self.parse_stmts()?;
-        self.expect(&Token::Symbol("}".to_string()))?;
+    fn expect(&mut self, expected: Expected) -> Result<(), ParseError> {
+        if !self.eat(expected) {
+            return Err(self.error(ParseErrorKind::UnexpectedToken));
+        }
         Ok(())
     }
 
-    fn parse_stmts(&mut self) -> Result<(), String> {
+    /// Parse the whole program, recovering from syntax errors so that every
+    /// error is reported. The returned AST contains `Item::Error` and
+    /// `Stmt::Error` placeholders wherever an item or a statement could not
+    /// be parsed.
+    pub fn parse_program(&mut self) -> (Program, Vec<ParseError>) {
+        // program -> items
+        // items -> item items | ε
+        let pos = self.current_token().position();
+        self.start_node(SyntaxKind::Program);
+        let mut items = Vec::new();
+        while !self.at_end() {
+            // A top-level block leaves the cache to its statements
+            let item = match self.cache.take() {
+                Some(mut cache) if !self.check(Expected::Symbol(Punct::LBrace)) => {
+                    let item = self.parse_item_cached(&mut cache);
+                    self.cache = Some(cache);
+                    item
+                }
+                cache => {
+                    self.cache = cache;
+                    self.parse_item()
+                }
+            };
+            items.push(item);
+        }
+        self.finish_node();
+        let program = Program { items, pos };
+        (program, std::mem::take(&mut self.errors))
+    }
+
+    fn parse_item(&mut self) -> Item {
+        let pos = self.current_token().position();
+        let (checkpoint, depth, scopes) = (self.checkpoint(), self.depth(), self.scopes.len());
+        match self.parse_item_inner() {
+            Ok(item) => item,
+            Err(err) => {
+                self.record(err);
+                self.scopes.truncate(scopes);
+                self.recover_tree(checkpoint, depth);
+                self.synchronize_item();
+                self.finish_node();
+                Item::Error { pos }
+            }
+        }
+    }
+
+    // Panic mode at file scope: skip past the `;` or the `}` that ends the
+    // broken item, passing over any braces in between. Always makes
+    // progress, so a stray `}` is skipped too.
+    fn synchronize_item(&mut self) {
+        let mut braces = 0usize;
+        while !self.at_end() {
+            let end = match &self.current_token().token {
+                Token::Symbol(Punct::LBrace) => {
+                    braces += 1;
+                    false
+                }
+                Token::Symbol(Punct::RBrace) => {
+                    braces = braces.saturating_sub(1);
+                    braces == 0
+                }
+                Token::Symbol(Punct::Semi) => braces == 0,
+                _ => false,
+            };
+            self.advance();
+            if end {
+                break;
+            }
+        }
+        self.expected.clear();
+    }
+
+    fn parse_item_inner(&mut self) -> Result<Item, ParseError> {
+        // item -> specifiers declarator block | declaration | block
+        let pos = self.current_token().position();
+        if self.check(Expected::Symbol(Punct::LBrace)) {
+            self.start_node(SyntaxKind::Block);
+            self.advance();
+            let block = self.parse_block_rest(pos);
+            self.finish_node();
+            return Ok(Item::Block(block));
+        }
+        if !self.check(Expected::Type) && !self.typedef_name_at(0) {
+            self.check_any(FIRST_ITEM);
+            return Err(self.error(ParseErrorKind::ExpectedDeclaration));
+        }
+
+        // A function definition is a declaration of a function up to its
+        // first declarator, followed by the body
+        let checkpoint = self.checkpoint();
+        let specifiers = self.parse_decl_specifiers(true)?;
+        if self.check(Expected::Symbol(Punct::Semi)) {
+            return Ok(Item::Decl(self.parse_declarators(checkpoint, specifiers, None, pos)?));
+        }
+        let declarator_checkpoint = self.checkpoint();
+        let declarator = self.parse_declarator(DeclaratorKind::Named)?;
+        let params = match declarator.derived.first() {
+            Some(Derived::Function { params, .. }) if self.check(Expected::Symbol(Punct::LBrace)) => params,
+            _ => {
+                let first = Some((declarator_checkpoint, declarator));
+                return Ok(Item::Decl(self.parse_declarators(checkpoint, specifiers, first, pos)?));
+            }
+        };
+
+        self.start_node_at(checkpoint, SyntaxKind::FunctionDef);
+        if let Some(name) = &declarator.name {
+            self.declare(&name.name, false);
+        }
+        // The parameters are in scope in the body
+        let names: Vec<_> = params.iter().filter_map(|param| param.declarator.name.clone()).collect();
+        self.scopes.push(HashMap::new());
+        for name in &names {
+            self.declare(&name.name, false);
+        }
+        let body_pos = self.current_token().position();
+        self.start_node(SyntaxKind::Block);
+        self.advance();
+        let body = self.parse_block_rest(body_pos);
+        self.finish_node();
+        self.scopes.pop();
+        self.finish_node();
+        Ok(Item::Function(FunctionDef { specifiers, declarator, body, pos }))
+    }
+
+    // block -> { stmts }
+    // The part of a block after `{`; a missing `}` is recorded, not returned
+    fn parse_block_rest(&mut self, pos: Position) -> Block {
+        self.scopes.push(HashMap::new());
+        let stmts = self.parse_stmts();
+        self.scopes.pop();
+        if let Err(err) = self.expect(Expected::Symbol(Punct::RBrace)) {
+            self.record(err);
+        }
+        Block { stmts, pos }
+    }
+
+    fn parse_stmts(&mut self) -> Vec<Stmt> {
         // stmts -> stmt stmts | ε
         // 尝试解析 stmt，如果失败或下一个是 } 则为空产生式
-        while self.current_token().token != Token::Symbol("}".to_string()) {
-            self.parse_stmt()?;
+        let mut stmts = Vec::new();
+        while !self.check(Expected::Symbol(Punct::RBrace)) && !self.at_end() {
+            // The cache is taken out while parsing, so only statements of
+            // a top-level block go through it
+            let stmt = match self.cache.take() {
+                Some(mut cache) => {
+                    let stmt = self.parse_stmt_cached(&mut cache);
+                    self.cache = Some(cache);
+                    stmt
+                }
+                None => self.parse_stmt(),
+            };
+            stmts.push(stmt);
         }
-        Ok(())
+        stmts
     }
 
-    fn parse_stmt(&mut self) -> Result<(), String> {
-        // 根据当前 token 来判断进入哪个产生式
-        match &self.current_token().token {
-            // id = expr ;
-            Token::Identifier(_) => {
-                let _id = self.current_token();
-                self.advance();
-                self.expect(&Token::Symbol("=".to_string()))?;
-                self.parse_expr()?;
-                self.expect(&Token::Symbol(";".to_string()))?;
+    // Take over the statement of the last parse that started at this token,
+    // if the cache has it, or parse it and put it in the cache
+    fn parse_stmt_cached(&mut self, cache: &mut StmtCache) -> Stmt {
+        match self.reuse(cache, true) {
+            Some(Cached::Stmt(stmt)) => stmt,
+            _ => self.parse_recorded(cache, Self::parse_stmt),
+        }
+    }
+
+    // The same for a top-level item
+    fn parse_item_cached(&mut self, cache: &mut StmtCache) -> Item {
+        match self.reuse(cache, false) {
+            Some(Cached::Item(item)) => item,
+            _ => self.parse_recorded(cache, Self::parse_item),
+        }
+    }
+
+    // What the cache has at this token, with its tokens skipped and its
+    // names and errors as if it was parsed again
+    fn reuse(&mut self, cache: &mut StmtCache, stmt: bool) -> Option<Cached> {
+        let first_token = self.tokens.consumed();
+        let (cached, parsed) = cache.take(first_token, stmt, &self.expected, |name| self.lookup(name))?;
+        for _ in 0..parsed.token_count {
+            self.tokens.bump();
+        }
+        for (name, typedef) in &parsed.names.declared {
+            self.declare(name, *typedef);
+        }
+        self.expected = parsed.expected_after.clone();
+        for err in &parsed.errors {
+            self.record(err.clone());
+        }
+        cache.push(parsed);
+        Some(cached)
+    }
+
+    fn parse_recorded<T>(&mut self, cache: &mut StmtCache, parse: impl FnOnce(&mut Self) -> T) -> T {
+        // Record the errors on their own; adding them to the others one by
+        // one afterwards drops the same cascades as `record` would have in
+        // place
+        let first_token = self.tokens.consumed();
+        let expected_before = self.expected.clone();
+        let outer = std::mem::take(&mut self.errors);
+        self.names = Some(Names { depth: self.scopes.len(), ..Names::default() });
+        let parsed = parse(self);
+        let names = self.names.take().expect("recording names");
+        let errors = std::mem::replace(&mut self.errors, outer);
+        for err in &errors {
+            self.record(err.clone());
+        }
+        cache.push(ParsedStmt {
+            first_token,
+            token_count: self.tokens.consumed() - first_token,
+            errors,
+            expected_before,
+            expected_after: self.expected.clone(),
+            names,
+        });
+        parsed
+    }
+
+    fn parse_stmt(&mut self) -> Stmt {
+        let pos = self.current_token().position();
+        let start = self.tokens.consumed();
+        let (checkpoint, depth, scopes) = (self.checkpoint(), self.depth(), self.scopes.len());
+        match self.parse_stmt_inner() {
+            Ok(stmt) => stmt,
+            Err(err) => {
+                self.record(err);
+                self.scopes.truncate(scopes);
+                self.recover_tree(checkpoint, depth);
+                self.synchronize(start);
+                self.finish_node();
+                Stmt::Error { pos }
             }
+        }
+    }
 
+    // Panic mode: skip tokens until one in FOLLOW(stmt) (`;`, `}`, `else`,
+    // `while`, and `case` and `default` of the next statement in a switch)
+    // so parsing can resume at a statement boundary. `;` ends the broken
+    // statement and is consumed; the others belong to the caller.
+    fn synchronize(&mut self, start: usize) {
+        // Always make progress, unless the offending token closes a block
+        if self.tokens.consumed() == start && !self.check(Expected::Symbol(Punct::RBrace)) {
+            self.advance();
+        }
+        while !self.at_end() {
+            match &self.current_token().token {
+                Token::Symbol(Punct::Semi) => {
+                    self.advance();
+                    break;
+                }
+                Token::Symbol(Punct::RBrace)
+                | Token::Keyword(Keyword::Else | Keyword::While | Keyword::Case | Keyword::Default) => break,
+                _ => self.advance(),
+            }
+        }
+        self.expected.clear();
+    }
+
+    fn parse_stmt_inner(&mut self) -> Result<Stmt, ParseError> {
+        let pos = self.current_token().position();
+        // id : stmt, before the name is taken for a type or an expression
+        if self.check(Expected::Identifier) && matches!(self.tokens.peek_nth(1).token, Token::Symbol(Punct::Colon)) {
+            self.start_node(SyntaxKind::LabeledStmt);
+            let label = self.parse_ident()?;
+            self.advance();
+            let body = Box::new(self.parse_stmt());
+            self.finish_node();
+            return Ok(Stmt::Labeled { label, body, pos });
+        }
+        if self.check(Expected::Type) || self.typedef_name_at(0) {
+            return Ok(Stmt::Decl(self.parse_declaration()?));
+        }
+
+        // 根据当前 token 来判断进入哪个产生式
+        let stmt = match &self.current_token().token {
             // if (bool) stmt restIf
-            Token::Keyword(k) if k == "if" => {
+            Token::Keyword(Keyword::If) => {
+                self.start_node(SyntaxKind::IfStmt);
                 self.advance();
-                self.expect(&Token::Symbol("(".to_string()))?;
-                self.parse_bool()?;
-                self.expect(&Token::Symbol(")".to_string()))?;
-                self.parse_stmt()?;
-                self.parse_rest_if()?;
+                let cond = self.parse_cond()?;
+                let then_branch = Box::new(self.parse_stmt());
+                let else_branch = self.parse_rest_if();
+                self.finish_node();
+                Stmt::If { cond, then_branch, else_branch, pos }
             }
 
             // while (bool) stmt
-            Token::Keyword(k) if k == "while" => {
+            Token::Keyword(Keyword::While) => {
+                self.start_node(SyntaxKind::WhileStmt);
+                self.advance();
+                let cond = self.parse_cond()?;
+                let body = Box::new(self.parse_stmt());
+                self.finish_node();
+                Stmt::While { cond, body, pos }
+            }
+
+            // do stmt while (bool) ;
+            Token::Keyword(Keyword::Do) => {
+                self.start_node(SyntaxKind::DoWhileStmt);
+                self.advance();
+                let body = Box::new(self.parse_stmt());
+                self.expect(Expected::Keyword(Keyword::While))?;
+                let cond = self.parse_cond()?;
+                self.eat(Expected::Symbol(Punct::Semi));
+                self.finish_node();
+                Stmt::DoWhile { body, cond, pos }
+            }
+
+            // for ( init cond ; step ) stmt
+            // init -> declaration | expr ; | ;
+            Token::Keyword(Keyword::For) => {
+                self.start_node(SyntaxKind::ForStmt);
+                self.advance();
+                self.expect(Expected::Symbol(Punct::LParen))?;
+                self.scopes.push(HashMap::new());
+                let init = if self.check(Expected::Type) || self.typedef_name_at(0) {
+                    Some(Box::new(ForInit::Decl(self.parse_declaration()?)))
+                } else {
+                    self.parse_optional_expr(Punct::Semi)?.map(|expr| Box::new(ForInit::Expr(expr)))
+                };
+                let cond = self.parse_optional_expr(Punct::Semi)?;
+                let step = self.parse_optional_expr(Punct::RParen)?;
+                let body = Box::new(self.parse_stmt());
+                self.scopes.pop();
+                self.finish_node();
+                Stmt::For { init, cond, step, body, pos }
+            }
+
+            // switch (expr) stmt
+            Token::Keyword(Keyword::Switch) => {
+                self.start_node(SyntaxKind::SwitchStmt);
+                self.advance();
+                let cond = self.parse_cond()?;
+                let body = Box::new(self.parse_stmt());
+                self.finish_node();
+                Stmt::Switch { cond, body, pos }
+            }
+
+            // case expr : stmt
+            Token::Keyword(Keyword::Case) => {
+                self.start_node(SyntaxKind::CaseStmt);
+                self.advance();
+                let value = self.parse_binary(Precedence::Conditional as u8)?;
+                self.expect(Expected::Symbol(Punct::Colon))?;
+                let body = Box::new(self.parse_stmt());
+                self.finish_node();
+                Stmt::Case { value, body, pos }
+            }
+
+            // default : stmt
+            Token::Keyword(Keyword::Default) => {
+                self.start_node(SyntaxKind::CaseStmt);
+                self.advance();
+                self.expect(Expected::Symbol(Punct::Colon))?;
+                let body = Box::new(self.parse_stmt());
+                self.finish_node();
+                Stmt::Default { body, pos }
+            }
+
+            // goto id ;
+            Token::Keyword(Keyword::Goto) => {
+                self.start_node(SyntaxKind::GotoStmt);
+                self.advance();
+                let label = self.parse_ident()?;
+                self.expect(Expected::Symbol(Punct::Semi))?;
+                self.finish_node();
+                Stmt::Goto { label, pos }
+            }
+
+            // continue ;
+            Token::Keyword(Keyword::Continue) => {
+                self.start_node(SyntaxKind::ContinueStmt);
                 self.advance();
-                self.expect(&Token::Symbol("(".to_string()))?;
-                self.parse_bool()?;
-                self.expect(&Token::Symbol(")".to_string()))?;
-                self.parse_stmt()?;
+                self.expect(Expected::Symbol(Punct::Semi))?;
+                self.finish_node();
+                Stmt::Continue { pos }
             }
 
-            // do stmt while (bool)
-            Token::Keyword(k) if k == "do" => {
+            // break ;
+            Token::Keyword(Keyword::Break) => {
+                self.start_node(SyntaxKind::BreakStmt);
                 self.advance();
-                self.parse_stmt()?;
-                self.expect(&Token::Keyword("while".to_string()))?;
-                self.expect(&Token::Symbol("(".to_string()))?;
-                self.parse_bool()?;
-                self.expect(&Token::Symbol(")".to_string()))?;
+                self.eat(Expected::Symbol(Punct::Semi));
+                self.finish_node();
+                Stmt::Break { pos }
             }
 
-            // break
-            Token::Keyword(k) if k == "break" => {
+            // return expr ; | return ;
+            Token::Keyword(Keyword::Return) => {
+                self.start_node(SyntaxKind::ReturnStmt);
                 self.advance();
+                let value = self.parse_optional_expr(Punct::Semi)?;
+                self.finish_node();
+                Stmt::Return { value, pos }
             }
 
             // block
-            Token::Symbol(s) if s == "{" => {
-                self.parse_block()?;
+            Token::Symbol(Punct::LBrace) => {
+                self.start_node(SyntaxKind::Block);
+                self.advance();
+                let block = self.parse_block_rest(pos);
+                self.finish_node();
+                Stmt::Block(block)
             }
 
-            _ => return Err(format!("Unexpected token: {:?}", self.current_token())),
-        }
+            // expr ; | ;
+            _ => {
+                if !self.check(Expected::Symbol(Punct::Semi)) && !self.at_expr() {
+                    self.check_any(FIRST_STMT);
+                    return Err(self.error(ParseErrorKind::ExpectedStatement));
+                }
+                self.start_node(SyntaxKind::ExprStmt);
+                let expr = self.parse_optional_expr(Punct::Semi)?;
+                self.finish_node();
+                Stmt::Expr { expr, pos }
+            }
+        };
 
-        Ok(())
+        Ok(stmt)
     }
 
-    fn parse_rest_if(&mut self) -> Result<(), String> {
+    // expr end | end, for the parts of a for loop and statements ending in `;`
+    fn parse_optional_expr(&mut self, end: Punct) -> Result<Option<Expr>, ParseError> {
+        let expr = if self.check(Expected::Symbol(end)) {
+            None
+        } else {
+            Some(self.parse_expr()?)
+        };
+        self.expect(Expected::Symbol(end))?;
+        Ok(expr)
+    }
+
+    fn parse_rest_if(&mut self) -> Option<Box<Stmt>> {
         // restIf -> else stmt | ε
-        if let Token::Keyword(k) = &self.current_token().token {
-            if k == "else" {
-                self.advance();
-                self.parse_stmt()?;
+        if self.check(Expected::Keyword(Keyword::Else)) {
+            self.start_node(SyntaxKind::ElseClause);
+            self.advance();
+            let stmt = self.parse_stmt();
+            self.finish_node();
+            return Some(Box::new(stmt));
+        }
+        None
+    }
+
+    // ( bool ) of if, while, do-while and switch. A malformed condition is
+    // replaced by an error expression so the statement around it still
+    // parses.
+    fn parse_cond(&mut self) -> Result<Expr, ParseError> {
+        self.start_node(SyntaxKind::Condition);
+        self.expect(Expected::Symbol(Punct::LParen))?;
+        let pos = self.current_token().position();
+        let (checkpoint, depth, scopes) = (self.checkpoint(), self.depth(), self.scopes.len());
+        match self.parse_expr() {
+            Ok(cond) => {
+                self.expect(Expected::Symbol(Punct::RParen))?;
+                self.finish_node();
+                Ok(cond)
+            }
+            Err(err) => {
+                self.record(err);
+                self.scopes.truncate(scopes);
+                self.recover_tree(checkpoint, depth);
+                let mut closed = false;
+                while !self.at_end() {
+                    match &self.current_token().token {
+                        Token::Symbol(Punct::RParen) => {
+                            closed = true;
+                            break;
+                        }
+                        Token::Symbol(Punct::Semi | Punct::LBrace | Punct::RBrace) => break,
+                        _ => self.advance(),
+                    }
+                }
+                self.finish_node();
+                if closed {
+                    self.advance();
+                }
+                self.finish_node();
+                self.expected.clear();
+                Ok(Expr::Error { pos })
             }
         }
-        Ok(())
     }
 
-    fn parse_bool(&mut self) -> Result<(), String> {
-        // bool -> expr bop
-        self.parse_expr()?;
-        self.parse_bop()?;
-        Ok(())
+    // expr -> expr , assign | assign, and so on down the operator table
+    fn parse_expr(&mut self) -> Result<Expr, ParseError> {
+        self.parse_binary(Precedence::Comma as u8)
+    }
+
+    // An expression without top-level commas, as on the right of `id =`
+    fn parse_assign(&mut self) -> Result<Expr, ParseError> {
+        self.parse_binary(Precedence::Assignment as u8)
+    }
+
+    // Precedence climbing: an operand, then every infix operator binding at
+    // least as tightly as `min`, each taking an operand that binds tighter
+    // still (left-associative) or as tightly (right-associative)
+    fn parse_binary(&mut self, min: u8) -> Result<Expr, ParseError> {
+        let checkpoint = self.checkpoint();
+        let mut lhs = self.parse_unary()?;
+        while let Some(infix) = self.infix().filter(|infix| infix.precedence as u8 >= min) {
+            let pos = self.current_token().position();
+            let next = infix.precedence as u8 + (infix.assoc == Assoc::Left) as u8;
+            let left = Box::new(lhs);
+            self.start_node_at(checkpoint, infix_kind(infix.op));
+            self.advance();
+            lhs = match infix.op {
+                InfixOp::Binary(op) => Expr::Binary { op, lhs: left, rhs: Box::new(self.parse_binary(next)?), pos },
+                InfixOp::Logical(op) => Expr::Logical { op, lhs: left, rhs: Box::new(self.parse_binary(next)?), pos },
+                InfixOp::Assign(op) => Expr::Assign { op, target: left, value: Box::new(self.parse_binary(next)?), pos },
+                InfixOp::Comma => Expr::Comma { lhs: left, rhs: Box::new(self.parse_binary(next)?), pos },
+                InfixOp::Conditional => {
+                    let then_branch = Box::new(self.parse_expr()?);
+                    self.expect(Expected::Symbol(Punct::Colon))?;
+                    let else_branch = Box::new(self.parse_binary(next)?);
+                    Expr::Conditional { cond: left, then_branch, else_branch, pos }
+                }
+            };
+            self.finish_node();
+        }
+        Ok(lhs)
+    }
+
+    // The infix operator at the current token, if any
+    fn infix(&self) -> Option<Infix> {
+        match self.current_token().token {
+            Token::Symbol(punct) => self.operators.infix(punct),
+            _ => None,
+        }
+    }
+
+    fn parse_unary(&mut self) -> Result<Expr, ParseError> {
+        // unary -> op unary | ( type-name ) unary | sizeof unary
+        //        | sizeof ( type-name ) | postfix
+        let pos = self.current_token().position();
+        let unary = Precedence::Unary as u8;
+        let punct = match self.current_token().token {
+            Token::Symbol(punct) => punct,
+            Token::Keyword(Keyword::Sizeof) => return self.parse_sizeof(),
+            _ => return self.parse_postfix(),
+        };
+        if let Some(op) = self.operators.prefix(punct) {
+            self.start_node(SyntaxKind::UnaryExpr);
+            self.advance();
+            let operand = Box::new(self.parse_binary(unary)?);
+            self.finish_node();
+            Ok(Expr::Unary { op, operand, pos })
+        } else if punct == Punct::LParen && self.at_type_name(1) {
+            self.start_node(SyntaxKind::CastExpr);
+            self.advance();
+            let ty = Box::new(self.parse_type_name()?);
+            self.expect(Expected::Symbol(Punct::RParen))?;
+            let expr = Box::new(self.parse_binary(unary)?);
+            self.finish_node();
+            Ok(Expr::Cast { ty, expr, pos })
+        } else {
+            self.parse_postfix()
+        }
+    }
+
+    fn parse_sizeof(&mut self) -> Result<Expr, ParseError> {
+        // sizeof unary | sizeof ( type-name )
+        let pos = self.current_token().position();
+        self.start_node(SyntaxKind::SizeofExpr);
+        self.advance();
+        let expr = if self.check(Expected::Symbol(Punct::LParen)) && self.at_type_name(1) {
+            self.advance();
+            let ty = Box::new(self.parse_type_name()?);
+            self.expect(Expected::Symbol(Punct::RParen))?;
+            Expr::SizeofType { ty, pos }
+        } else {
+            let expr = Box::new(self.parse_binary(Precedence::Unary as u8)?);
+            Expr::SizeofExpr { expr, pos }
+        };
+        self.finish_node();
+        Ok(expr)
     }
 
-    fn parse_bop(&mut self) -> Result<(), String> {
-        // bop -> < expr | <= expr | > expr | >= expr | ε
+    // Whether the current token starts an expression
+    fn at_expr(&self) -> bool {
         match &self.current_token().token {
-            Token::Symbol(sym) if ["<", "<=", ">", ">="].contains(&sym.as_str()) => {
-                self.advance();
-                self.parse_expr()?;
+            Token::Identifier(_) | Token::Number { .. } | Token::CharLiteral { .. } | Token::StringLiteral { .. } => true,
+            Token::Keyword(keyword) => *keyword == Keyword::Sizeof,
+            Token::Symbol(punct) => *punct == Punct::LParen || self.operators.prefix(*punct).is_some(),
+            _ => false,
+        }
+    }
+
+    // Whether the token `n` places on starts a type name
+    fn at_type_name(&mut self, n: usize) -> bool {
+        match &self.tokens.peek_nth(n).token {
+            Token::Keyword(keyword) => TYPE_KEYWORDS.contains(keyword),
+            _ => self.typedef_name_at(n),
+        }
+    }
+
+    // Whether the token `n` places on is a typedef name
+    fn typedef_name_at(&mut self, n: usize) -> bool {
+        match &self.tokens.peek_nth(n).token {
+            Token::Identifier(name) => {
+                let name = translate(name.as_ref(), self.trigraphs).into_owned();
+                self.is_typedef(&name)
             }
-            _ => {}
+            _ => false,
         }
-        Ok(())
     }
 
-    fn parse_expr(&mut self) -> Result<(), String> {
-        // expr -> term expr'
-        self.parse_term()?;
-        self.parse_expr_prime()?;
-        Ok(())
+    fn parse_type_name(&mut self) -> Result<TypeName, ParseError> {
+        // type-name -> specifiers abstract-declarator
+        let pos = self.current_token().position();
+        self.start_node(SyntaxKind::TypeName);
+        let specifiers = self.parse_decl_specifiers(false)?;
+        let declarator = self.parse_declarator(DeclaratorKind::Abstract)?;
+        self.finish_node();
+        Ok(TypeName { specifiers, declarator, pos })
     }
 
-    fn parse_expr_prime(&mut self) -> Result<(), String> {
-        // expr' -> + term expr' | - term expr' | ε
-        while let Token::Symbol(sym) = &self.current_token().token {
-            if sym == "+" || sym == "-" {
-                self.advance(); // + or -
-                self.parse_term()?;
-            } else {
+    fn parse_postfix(&mut self) -> Result<Expr, ParseError> {
+        // postfix -> postfix [ expr ] | postfix ( args ) | postfix . id
+        //          | postfix -> id | postfix ++ | postfix -- | primary
+        // args -> assign , args | assign | ε
+        let checkpoint = self.checkpoint();
+        let mut expr = self.parse_primary()?;
+        while matches!(self.current_token().token, Token::Symbol(_)) {
+            let pos = self.current_token().position();
+            let base = Box::new(expr);
+            expr = match self.current_token().token {
+                Token::Symbol(Punct::LBracket) => {
+                    self.start_node_at(checkpoint, SyntaxKind::IndexExpr);
+                    self.advance();
+                    let index = Box::new(self.parse_expr()?);
+                    self.expect(Expected::Symbol(Punct::RBracket))?;
+                    Expr::Index { base, index, pos }
+                }
+                Token::Symbol(Punct::LParen) => {
+                    self.start_node_at(checkpoint, SyntaxKind::CallExpr);
+                    self.advance();
+                    let mut args = Vec::new();
+                    if !self.check(Expected::Symbol(Punct::RParen)) {
+                        loop {
+                            args.push(self.parse_assign()?);
+                            if !self.eat(Expected::Symbol(Punct::Comma)) {
+                                break;
+                            }
+                        }
+                    }
+                    self.expect(Expected::Symbol(Punct::RParen))?;
+                    Expr::Call { callee: base, args, pos }
+                }
+                Token::Symbol(punct @ (Punct::Dot | Punct::Arrow)) => {
+                    self.start_node_at(checkpoint, SyntaxKind::MemberExpr);
+                    self.advance();
+                    let member = self.parse_ident()?;
+                    Expr::Member { base, member, arrow: punct == Punct::Arrow, pos }
+                }
+                Token::Symbol(punct) if self.operators.postfix(punct).is_some() => {
+                    let op = self.operators.postfix(punct).expect("checked above");
+                    self.start_node_at(checkpoint, SyntaxKind::PostfixExpr);
+                    self.advance();
+                    Expr::Unary { op, operand: base, pos }
+                }
+                _ => return Ok(*base),
+            };
+            self.finish_node();
+        }
+        Ok(expr)
+    }
+
+    fn parse_primary(&mut self) -> Result<Expr, ParseError> {
+        // primary -> ( expr ) | id | num | char | strings
+        let pos = self.current_token().position();
+        let expr = match &self.current_token().token {
+            Token::Symbol(Punct::LParen) => {
+                self.start_node(SyntaxKind::ParenExpr);
+                self.advance();
+                let expr = Box::new(self.parse_expr()?);
+                self.expect(Expected::Symbol(Punct::RParen))?;
+                self.finish_node();
+                Expr::Paren { expr, pos }
+            }
+            Token::Identifier(_) => {
+                self.start_node(SyntaxKind::NameExpr);
+                let ident = self.parse_ident()?;
+                self.finish_node();
+                Expr::Ident(ident)
+            }
+            Token::Number { literal, kind, base, suffix, .. } => {
+                let number = Number {
+                    literal: literal.as_ref().to_string(),
+                    kind: *kind,
+                    base: *base,
+                    suffix: *suffix,
+                    pos,
+                };
+                self.start_node(SyntaxKind::Literal);
+                self.advance();
+                self.finish_node();
+                Expr::Num(number)
+            }
+            Token::CharLiteral { value, .. } => {
+                let value = *value;
+                self.start_node(SyntaxKind::Literal);
+                self.advance();
+                self.finish_node();
+                Expr::Char { value, pos }
+            }
+            // Adjacent string literals are one
+            Token::StringLiteral { .. } => {
+                self.start_node(SyntaxKind::Literal);
+                let mut value = Vec::new();
+                while self.check(Expected::StringLiteral) {
+                    if let Token::StringLiteral { value: part, .. } = &self.current_token().token {
+                        value.extend_from_slice(part);
+                    }
+                    self.advance();
+                }
+                self.finish_node();
+                Expr::Str { value, pos }
+            }
+            _ => {
+                self.check_any(FIRST_EXPR);
+                return Err(self.error(ParseErrorKind::ExpectedExpression));
+            }
+        };
+        Ok(expr)
+    }
+
+    fn parse_declaration(&mut self) -> Result<Declaration, ParseError> {
+        // declaration -> specifiers init-declarators ; | specifiers ;
+        let pos = self.current_token().position();
+        let checkpoint = self.checkpoint();
+        let specifiers = self.parse_decl_specifiers(true)?;
+        self.parse_declarators(checkpoint, specifiers, None, pos)
+    }
+
+    // The rest of a declaration after its specifiers, which started at
+    // `checkpoint`. At file scope the first declarator is parsed before
+    // knowing it is not a function definition, and comes in as `first`.
+    fn parse_declarators(
+        &mut self,
+        checkpoint: usize,
+        specifiers: DeclSpecifiers,
+        mut first: Option<(usize, Declarator)>,
+        pos: Position,
+    ) -> Result<Declaration, ParseError> {
+        // init-declarators -> init-declarator , init-declarators | init-declarator
+        // init-declarator -> declarator | declarator = initializer
+        self.start_node_at(checkpoint, SyntaxKind::Declaration);
+        let mut declarators = Vec::new();
+        if first.is_some() || !self.check(Expected::Symbol(Punct::Semi)) {
+            loop {
+                let declarator = match first.take() {
+                    Some((checkpoint, declarator)) => {
+                        self.start_node_at(checkpoint, SyntaxKind::InitDeclarator);
+                        declarator
+                    }
+                    None => {
+                        self.start_node(SyntaxKind::InitDeclarator);
+                        self.parse_declarator(DeclaratorKind::Named)?
+                    }
+                };
+                // The name is in scope from the end of its declarator on
+                if let Some(name) = &declarator.name {
+                    self.declare(&name.name, specifiers.is_typedef());
+                }
+                let init = if self.eat(Expected::Symbol(Punct::Eq)) {
+                    Some(self.parse_initializer()?)
+                } else {
+                    None
+                };
+                self.finish_node();
+                declarators.push(InitDeclarator { declarator, init, width: None });
+                if !self.eat(Expected::Symbol(Punct::Comma)) {
+                    break;
+                }
+            }
+        }
+        self.expect(Expected::Symbol(Punct::Semi))?;
+        self.finish_node();
+        Ok(Declaration { specifiers, declarators, pos })
+    }
+
+    // Storage classes and function specifiers only where `storage` allows,
+    // not in type names and struct fields
+    fn parse_decl_specifiers(&mut self, storage: bool) -> Result<DeclSpecifiers, ParseError> {
+        // specifiers -> specifier specifiers | specifier
+        let pos = self.current_token().position();
+        self.start_node(SyntaxKind::DeclSpecifiers);
+        let mut specifiers = DeclSpecifiers {
+            storage: Vec::new(),
+            qualifiers: Vec::new(),
+            function: Vec::new(),
+            ty: TypeSpecifier::Builtin(Vec::new()),
+            pos,
+        };
+        let mut has_type = false;
+        loop {
+            self.check(Expected::Type);
+            // A typedef name is only one before any other type specifier
+            if !has_type && self.typedef_name_at(0) {
+                specifiers.ty = TypeSpecifier::Typedef(self.parse_ident()?);
+                has_type = true;
+                continue;
+            }
+            let Token::Keyword(keyword) = self.current_token().token else {
                 break;
+            };
+            match (keyword, &mut specifiers.ty) {
+                (Keyword::Const | Keyword::Volatile | Keyword::Restrict | Keyword::_Atomic, _) => {
+                    specifiers.qualifiers.push(keyword);
+                }
+                (Keyword::Inline | Keyword::_Noreturn, _) if storage => specifiers.function.push(keyword),
+                (
+                    Keyword::Typedef
+                    | Keyword::Extern
+                    | Keyword::Static
+                    | Keyword::_ThreadLocal
+                    | Keyword::ThreadLocal
+                    | Keyword::Auto
+                    | Keyword::Register,
+                    _,
+                ) if storage => specifiers.storage.push(keyword),
+                (Keyword::Struct | Keyword::Union, _) if !has_type => {
+                    specifiers.ty = TypeSpecifier::Struct(self.parse_struct_specifier()?);
+                    has_type = true;
+                    continue;
+                }
+                (Keyword::Enum, _) if !has_type => {
+                    specifiers.ty = TypeSpecifier::Enum(self.parse_enum_specifier()?);
+                    has_type = true;
+                    continue;
+                }
+                (_, TypeSpecifier::Builtin(builtin)) if TYPE_KEYWORDS.contains(&keyword) => {
+                    builtin.push(keyword);
+                    has_type = true;
+                }
+                _ => break,
             }
+            self.advance();
         }
-        Ok(())
+        if !has_type {
+            return Err(self.error(ParseErrorKind::UnexpectedToken));
+        }
+        self.finish_node();
+        Ok(specifiers)
     }
 
-    fn parse_term(&mut self) -> Result<(), String> {
-        // term -> factor term'
-        self.parse_factor()?;
-        self.parse_term_prime()?;
-        Ok(())
+    fn parse_struct_specifier(&mut self) -> Result<StructSpecifier, ParseError> {
+        // struct-specifier -> struct id { fields } | struct id | struct { fields }
+        // fields -> field fields | ε
+        let pos = self.current_token().position();
+        self.start_node(SyntaxKind::StructSpecifier);
+        let union = matches!(self.current_token().token, Token::Keyword(Keyword::Union));
+        self.advance();
+        let tag = if self.check(Expected::Identifier) {
+            Some(self.parse_ident()?)
+        } else {
+            None
+        };
+        let mut fields = None;
+        if tag.is_none() || self.check(Expected::Symbol(Punct::LBrace)) {
+            self.expect(Expected::Symbol(Punct::LBrace))?;
+            let mut list = Vec::new();
+            while !self.check(Expected::Symbol(Punct::RBrace)) && !self.at_end() {
+                list.push(self.parse_field()?);
+            }
+            self.expect(Expected::Symbol(Punct::RBrace))?;
+            fields = Some(list);
+        }
+        self.finish_node();
+        Ok(StructSpecifier { union, tag, fields, pos })
     }
 
-    fn parse_term_prime(&mut self) -> Result<(), String> {
-        // term' -> * factor term' | / factor term' | ε
-        while let Token::Symbol(sym) = &self.current_token().token {
-            if sym == "*" || sym == "/" {
-                self.advance(); // * or /
-                self.parse_factor()?;
+    fn parse_field(&mut self) -> Result<Declaration, ParseError> {
+        // field -> specifiers field-declarators ;
+        // field-declarators -> field-declarator , field-declarators | field-declarator
+        // field-declarator -> declarator | declarator : expr | : expr
+        let pos = self.current_token().position();
+        self.start_node(SyntaxKind::Declaration);
+        let specifiers = self.parse_decl_specifiers(false)?;
+        let mut declarators = Vec::new();
+        loop {
+            self.start_node(SyntaxKind::InitDeclarator);
+            // An unnamed bit-field has only its width
+            let declarator = if self.check(Expected::Symbol(Punct::Colon)) {
+                Declarator { name: None, derived: Vec::new(), pos: self.current_token().position() }
+            } else {
+                self.parse_declarator(DeclaratorKind::Named)?
+            };
+            let width = if self.eat(Expected::Symbol(Punct::Colon)) {
+                Some(self.parse_binary(Precedence::Conditional as u8)?)
             } else {
+                None
+            };
+            self.finish_node();
+            declarators.push(InitDeclarator { declarator, init: None, width });
+            if !self.eat(Expected::Symbol(Punct::Comma)) {
                 break;
             }
         }
-        Ok(())
+        self.expect(Expected::Symbol(Punct::Semi))?;
+        self.finish_node();
+        Ok(Declaration { specifiers, declarators, pos })
     }
 
-    fn parse_factor(&mut self) -> Result<(), String> {
-        // factor -> ( expr ) | id | num
-        match &self.current_token().token {
-            Token::Symbol(s) if s == "(" => {
+    fn parse_enum_specifier(&mut self) -> Result<EnumSpecifier, ParseError> {
+        // enum-specifier -> enum id { enumerators } | enum id | enum { enumerators }
+        // enumerators -> enumerator | enumerator , | enumerator , enumerators
+        // enumerator -> id | id = expr
+        let pos = self.current_token().position();
+        self.start_node(SyntaxKind::EnumSpecifier);
+        self.advance();
+        let tag = if self.check(Expected::Identifier) {
+            Some(self.parse_ident()?)
+        } else {
+            None
+        };
+        let mut enumerators = None;
+        if tag.is_none() || self.check(Expected::Symbol(Punct::LBrace)) {
+            self.expect(Expected::Symbol(Punct::LBrace))?;
+            let mut list = Vec::new();
+            loop {
+                self.start_node(SyntaxKind::Enumerator);
+                let name = self.parse_ident()?;
+                let value = if self.eat(Expected::Symbol(Punct::Eq)) {
+                    Some(self.parse_binary(Precedence::Conditional as u8)?)
+                } else {
+                    None
+                };
+                self.finish_node();
+                self.declare(&name.name, false);
+                list.push(Enumerator { name, value });
+                if !self.eat(Expected::Symbol(Punct::Comma)) || self.check(Expected::Symbol(Punct::RBrace)) {
+                    break;
+                }
+            }
+            self.expect(Expected::Symbol(Punct::RBrace))?;
+            enumerators = Some(list);
+        }
+        self.finish_node();
+        Ok(EnumSpecifier { tag, enumerators, pos })
+    }
+
+    fn parse_declarator(&mut self, kind: DeclaratorKind) -> Result<Declarator, ParseError> {
+        // declarator -> pointers direct suffixes
+        // pointers -> * qualifiers pointers | ε
+        // direct -> id | ( declarator ) | ε, the last only when abstract
+        // suffixes -> [ expr ] suffixes | [ ] suffixes | ( params ) suffixes | ε
+        let pos = self.current_token().position();
+        self.start_node(SyntaxKind::Declarator);
+        let mut pointers = Vec::new();
+        while self.eat(Expected::Symbol(Punct::Star)) {
+            let mut qualifiers = Vec::new();
+            while let Token::Keyword(
+                keyword @ (Keyword::Const | Keyword::Volatile | Keyword::Restrict | Keyword::_Atomic),
+            ) = self.current_token().token
+            {
+                qualifiers.push(keyword);
                 self.advance();
-                self.parse_expr()?;
-                self.expect(&Token::Symbol(")".to_string()))?;
             }
-            Token::Identifier(_) | Token::Number { .. } => {
+            pointers.push(Derived::Pointer { qualifiers });
+        }
+
+        let (name, mut derived) = if kind != DeclaratorKind::Abstract && self.check(Expected::Identifier) {
+            (Some(self.parse_ident()?), Vec::new())
+        } else if self.check(Expected::Symbol(Punct::LParen)) && self.at_nested_declarator(kind) {
+            self.advance();
+            let inner = self.parse_declarator(kind)?;
+            self.expect(Expected::Symbol(Punct::RParen))?;
+            (inner.name, inner.derived)
+        } else if kind == DeclaratorKind::Named {
+            return Err(self.error(ParseErrorKind::UnexpectedToken));
+        } else {
+            (None, Vec::new())
+        };
+
+        loop {
+            if self.check(Expected::Symbol(Punct::LBracket)) {
                 self.advance();
+                let len = if self.check(Expected::Symbol(Punct::RBracket)) {
+                    None
+                } else {
+                    Some(Box::new(self.parse_assign()?))
+                };
+                self.expect(Expected::Symbol(Punct::RBracket))?;
+                derived.push(Derived::Array { len });
+            } else if self.check(Expected::Symbol(Punct::LParen)) {
+                derived.push(self.parse_params()?);
+            } else {
+                break;
             }
-            _ => return Err(format!("Unexpected token in factor: {:?}", self.current_token())),
         }
-        Ok(())
+        // `*` binds looser than the suffixes, the one next to the name first
+        derived.extend(pointers.into_iter().rev());
+        self.finish_node();
+        Ok(Declarator { name, derived, pos })
+    }
+
+    // At `(`: whether it opens a parenthesized declarator rather than a
+    // parameter list
+    fn at_nested_declarator(&mut self, kind: DeclaratorKind) -> bool {
+        if kind == DeclaratorKind::Named {
+            return true;
+        }
+        match self.tokens.peek_nth(1).token {
+            Token::Symbol(Punct::Star | Punct::LParen | Punct::LBracket) => true,
+            Token::Identifier(_) => kind == DeclaratorKind::Either && !self.typedef_name_at(1),
+            _ => false,
+        }
+    }
+
+    fn parse_params(&mut self) -> Result<Derived, ParseError> {
+        // ( params ) -> ( ) | ( param-list ) | ( param-list , ... )
+        // param-list -> param , param-list | param
+        // param -> specifiers declarator | specifiers abstract-declarator
+        self.start_node(SyntaxKind::ParamList);
+        self.advance();
+        // Parameter names are only in scope within the prototype
+        self.scopes.push(HashMap::new());
+        let mut params = Vec::new();
+        let mut variadic = false;
+        if !self.check(Expected::Symbol(Punct::RParen)) {
+            loop {
+                if !params.is_empty() && self.eat(Expected::Symbol(Punct::Ellipsis)) {
+                    variadic = true;
+                    break;
+                }
+                self.start_node(SyntaxKind::Param);
+                let specifiers = self.parse_decl_specifiers(true)?;
+                let declarator = self.parse_declarator(DeclaratorKind::Either)?;
+                if let Some(name) = &declarator.name {
+                    self.declare(&name.name, false);
+                }
+                self.finish_node();
+                params.push(Param { specifiers, declarator });
+                if !self.eat(Expected::Symbol(Punct::Comma)) {
+                    break;
+                }
+            }
+        }
+        self.scopes.pop();
+        self.expect(Expected::Symbol(Punct::RParen))?;
+        self.finish_node();
+        Ok(Derived::Function { params, variadic })
+    }
+
+    fn parse_initializer(&mut self) -> Result<Initializer, ParseError> {
+        // initializer -> expr | { initializers }
+        // initializers -> initializer | initializer , | initializer , initializers | ε
+        if !self.check(Expected::Symbol(Punct::LBrace)) {
+            return Ok(Initializer::Expr(self.parse_assign()?));
+        }
+        let pos = self.current_token().position();
+        self.start_node(SyntaxKind::InitializerList);
+        self.advance();
+        let mut items = Vec::new();
+        while !self.check(Expected::Symbol(Punct::RBrace)) {
+            items.push(self.parse_initializer()?);
+            if !self.eat(Expected::Symbol(Punct::Comma)) {
+                break;
+            }
+        }
+        self.expect(Expected::Symbol(Punct::RBrace))?;
+        self.finish_node();
+        Ok(Initializer::List { items, pos })
+    }
+
+    fn parse_ident(&mut self) -> Result<Ident, ParseError> {
+        let pos = self.current_token().position();
+        if let Token::Identifier(name) = &self.current_token().token {
+            let ident = Ident { name: translate(name.as_ref(), self.trigraphs).into_owned(), pos };
+            self.advance();
+            return Ok(ident);
+        }
+        self.check(Expected::Identifier);
+        Err(self.error(ParseErrorKind::UnexpectedToken))
+    }
+}
+
+// The syntax tree node an infix operator makes
+fn infix_kind(op: InfixOp) -> SyntaxKind {
+    match op {
+        InfixOp::Binary(_)   => SyntaxKind::BinaryExpr,
+        InfixOp::Logical(_)  => SyntaxKind::LogicalExpr,
+        InfixOp::Assign(_)   => SyntaxKind::AssignExpr,
+        InfixOp::Conditional => SyntaxKind::ConditionalExpr,
+        InfixOp::Comma       => SyntaxKind::CommaExpr,
     }
 }
@@ -0,0 +1,1162 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+use crate::config::LexerConfig;
+use crate::lexer::{translate, Lexer};
+use crate::number::Constant;
+use crate::token::{Keyword, NumberBase, NumberKind, PositionedToken, Punct, Span, Token, TokenKind};
+
+// How deep `#include`s may nest before a file is taken to include itself
+const MAX_INCLUDE_DEPTH: usize = 200;
+
+/// A file the preprocessor has read, indexing `Preprocessor::file`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FileId(usize);
+
+/// A span in one of the preprocessor's files.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Location {
+    pub file: FileId,
+    pub span: Span,
+}
+
+/// A token coming out of the preprocessor.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExpandedToken {
+    pub token: Token,
+    /// Where the token is spelled: in the source text, in a macro definition
+    /// or in a macro argument.
+    pub spelling: Location,
+    /// The outermost macro invocation the token came out of, `None` if it
+    /// was not produced by a macro.
+    pub expansion: Option<Location>,
+}
+
+impl ExpandedToken {
+    /// Where the user sees the token: the invocation for tokens produced by
+    /// a macro, the spelling otherwise.
+    pub fn location(&self) -> Location {
+        self.expansion.unwrap_or(self.spelling)
+    }
+
+    /// The token at its `location`, for the parser. Its span is in the file
+    /// of the location, which is not the main file for included tokens.
+    pub fn into_positioned(self) -> PositionedToken {
+        PositionedToken { span: self.location().span, token: self.token }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PreprocessError {
+    pub message: String,
+    pub location: Location,
+}
+
+impl fmt::Display for PreprocessError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for PreprocessError {}
+
+pub struct SourceFile {
+    name: String,
+    // Where it was read from, `None` for virtual files
+    path: Option<PathBuf>,
+    text: String,
+    tokens: Vec<PpToken>,
+}
+
+impl SourceFile {
+    /// The name the file was given or included as.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+}
+
+// A preprocessing token with what expansion needs to know about it
+#[derive(Debug, Clone)]
+struct PpToken {
+    token: Token,
+    // The exact source text, for `#` and `##`
+    text: String,
+    spelling: Location,
+    expansion: Option<Location>,
+    // Whitespace or a comment comes before it
+    space: bool,
+    // First on its line, so a `#` here starts a directive
+    line_start: bool,
+    // Macros whose expansion produced the token, which must not expand it
+    // again
+    hide: Vec<Rc<str>>,
+}
+
+impl PpToken {
+    // Macro names and parameters may be keywords as well
+    fn ident(&self) -> Option<&str> {
+        match self.token {
+            Token::Identifier(_) | Token::Keyword(_) => Some(&self.text),
+            _ => None,
+        }
+    }
+
+    fn is(&self, text: &str) -> bool {
+        self.text == text
+    }
+
+    fn is_punct(&self, punct: Punct) -> bool {
+        self.token == Token::Symbol(punct)
+    }
+}
+
+struct Macro {
+    // `None` for object-like macros; a variadic macro's last parameter is
+    // `__VA_ARGS__`
+    params: Option<Vec<String>>,
+    variadic: bool,
+    body: Vec<PpToken>,
+}
+
+impl Macro {
+    fn param(&self, token: &PpToken) -> Option<usize> {
+        let name = token.ident()?;
+        self.params.as_ref()?.iter().position(|p| p == name)
+    }
+
+    // Same parameters and body tokens, with whitespace in the same places
+    fn same_as(&self, other: &Macro) -> bool {
+        self.params == other.params
+            && self.body.len() == other.body.len()
+            && self
+                .body
+                .iter()
+                .zip(&other.body)
+                .enumerate()
+                .all(|(i, (a, b))| a.text == b.text && (i == 0 || a.space == b.space))
+    }
+}
+
+// An open `#if` group
+struct Cond {
+    location: Location,
+    // One of its branches has been taken
+    taken: bool,
+    seen_else: bool,
+}
+
+// A file being read
+struct Reader {
+    file: FileId,
+    next: usize,
+    conds: Vec<Cond>,
+}
+
+/// Runs the C preprocessor over token streams from the lexer: `#include`,
+/// `#define` and `#undef`, conditional compilation, and macro expansion
+/// with `#` and `##`. Comments are dropped; lexer errors pass through as
+/// `Token::Error` for the parser to report.
+///
+/// ```
+/// use lexer_and_parser::{Preprocessor, Token};
+///
+/// let mut pp = Preprocessor::new().with_file("size.h", "#define SIZE(n) (n * 4)");
+/// let (tokens, errors) = pp.preprocess("main.c", "#include \"size.h\"\nx = SIZE(2);");
+/// let text: Vec<_> = tokens.iter().map(|t| t.token.to_string()).collect();
+/// assert_eq!(text.join(" "), "x = ( 2 * 4 ) ;");
+/// assert!(errors.is_empty());
+/// ```
+pub struct Preprocessor {
+    config: LexerConfig,
+    include_dirs: Vec<PathBuf>,
+    virtual_files: HashMap<String, FileId>,
+    disk_files: HashMap<PathBuf, FileId>,
+    files: Vec<SourceFile>,
+    // The `#define`s and the main file of `preprocess`, replaced by each
+    // run rather than added to `files` again
+    command_line: Option<FileId>,
+    main: Option<FileId>,
+    // `#define`s to start each run with
+    predefined: String,
+    macros: HashMap<Rc<str>, Rc<Macro>>,
+    errors: Vec<PreprocessError>,
+    // Files being read, the innermost last
+    stack: Vec<Reader>,
+    // Tokens to read before going back to the file, the next one last:
+    // macro expansions waiting to be rescanned, or a macro argument
+    pending: Vec<PpToken>,
+    // Reading a macro argument, which ends with `pending`
+    isolated: bool,
+}
+
+impl Default for Preprocessor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Preprocessor {
+    /// A preprocessor lexing with the default C17 config.
+    pub fn new() -> Self {
+        Self::with_config(LexerConfig::default())
+    }
+
+    pub fn with_config(config: LexerConfig) -> Self {
+        Preprocessor {
+            config,
+            include_dirs: Vec::new(),
+            virtual_files: HashMap::new(),
+            disk_files: HashMap::new(),
+            files: Vec::new(),
+            command_line: None,
+            main: None,
+            predefined: String::new(),
+            macros: HashMap::new(),
+            errors: Vec::new(),
+            stack: Vec::new(),
+            pending: Vec::new(),
+            isolated: false,
+        }
+    }
+
+    /// Search `dir` for included files, after the directories added before.
+    pub fn with_include_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.include_dirs.push(dir.into());
+        self
+    }
+
+    /// A file that exists only in memory, found by `#include` under `name`
+    /// before the include directories are searched.
+    pub fn with_file(mut self, name: impl Into<String>, text: impl Into<String>) -> Self {
+        let name = name.into();
+        let id = self.add_file(name.clone(), None, text.into());
+        self.virtual_files.insert(name, id);
+        self
+    }
+
+    /// Define `name` as `value` before every run, like `-Dname=value`.
+    pub fn with_define(mut self, name: &str, value: &str) -> Self {
+        self.predefined.push_str(&format!("#define {} {}\n", name, value));
+        self
+    }
+
+    pub fn file(&self, id: FileId) -> &SourceFile {
+        &self.files[id.0]
+    }
+
+    /// Preprocess `text` as the main file `name`. Each run starts with only
+    /// the predefined macros, and its main file takes the `FileId` of the
+    /// previous run's.
+    pub fn preprocess(&mut self, name: &str, text: &str) -> (Vec<ExpandedToken>, Vec<PreprocessError>) {
+        let main = self.replace_file(self.main, name.to_string(), text.to_string());
+        self.main = Some(main);
+        self.run(main)
+    }
+
+    /// Preprocess the file at `path`; `#include "..."` looks next to it first.
+    pub fn preprocess_file(
+        &mut self,
+        path: impl AsRef<Path>,
+    ) -> std::io::Result<(Vec<ExpandedToken>, Vec<PreprocessError>)> {
+        let main = self.load(path.as_ref())?;
+        Ok(self.run(main))
+    }
+
+    fn run(&mut self, main: FileId) -> (Vec<ExpandedToken>, Vec<PreprocessError>) {
+        self.macros.clear();
+        self.errors.clear();
+        self.pending.clear();
+
+        // The defines are only lexed again after `with_define` changed them
+        let predefined = match self.command_line {
+            Some(id) if self.files[id.0].text == self.predefined => id,
+            slot => self.replace_file(slot, "<command line>".to_string(), self.predefined.clone()),
+        };
+        self.command_line = Some(predefined);
+        self.stack.push(Reader { file: predefined, next: 0, conds: Vec::new() });
+        while self.next_unexpanded().is_some() {}
+
+        self.stack.push(Reader { file: main, next: 0, conds: Vec::new() });
+        let mut tokens = Vec::new();
+        while let Some(token) = self.next_expanded() {
+            tokens.push(ExpandedToken { token: token.token, spelling: token.spelling, expansion: token.expansion });
+        }
+        (tokens, std::mem::take(&mut self.errors))
+    }
+
+    fn add_file(&mut self, name: String, path: Option<PathBuf>, text: String) -> FileId {
+        let id = FileId(self.files.len());
+        let tokens = lex(&self.config, id, &text);
+        self.files.push(SourceFile { name, path, text, tokens });
+        id
+    }
+
+    // A virtual file in place of the one at `slot`, or a new one
+    fn replace_file(&mut self, slot: Option<FileId>, name: String, text: String) -> FileId {
+        match slot {
+            Some(id) => {
+                let tokens = lex(&self.config, id, &text);
+                self.files[id.0] = SourceFile { name, path: None, text, tokens };
+                id
+            }
+            None => self.add_file(name, None, text),
+        }
+    }
+
+    // Read a file from disk once, later includes share it
+    fn load(&mut self, path: &Path) -> std::io::Result<FileId> {
+        if let Some(&id) = self.disk_files.get(path) {
+            return Ok(id);
+        }
+        let text = std::fs::read_to_string(path)?;
+        let id = self.add_file(path.display().to_string(), Some(path.to_path_buf()), text);
+        self.disk_files.insert(path.to_path_buf(), id);
+        Ok(id)
+    }
+
+    fn error(&mut self, location: Location, message: impl Into<String>) {
+        self.errors.push(PreprocessError { message: message.into(), location });
+    }
+
+    // The next token after directives, before macro expansion
+    fn next_unexpanded(&mut self) -> Option<PpToken> {
+        if let Some(token) = self.pending.pop() {
+            return Some(token);
+        }
+        if self.isolated {
+            return None;
+        }
+        loop {
+            let reader = self.stack.last_mut()?;
+            match self.files[reader.file.0].tokens.get(reader.next) {
+                Some(token) if token.line_start && token.is_punct(Punct::Hash) => self.directive(),
+                Some(token) => {
+                    reader.next += 1;
+                    return Some(token.clone());
+                }
+                None => {
+                    let reader = self.stack.pop().expect("reading a file");
+                    for cond in reader.conds {
+                        self.error(cond.location, "unterminated conditional directive");
+                    }
+                }
+            }
+        }
+    }
+
+    // The next token after macro expansion
+    fn next_expanded(&mut self) -> Option<PpToken> {
+        loop {
+            let token = self.next_unexpanded()?;
+            let found = token
+                .ident()
+                .filter(|name| !token.hide.iter().any(|hidden| &**hidden == *name))
+                .and_then(|name| self.macros.get_key_value(name));
+            let (name, mac) = match found {
+                Some((name, mac)) => (name.clone(), mac.clone()),
+                None => return Some(token),
+            };
+
+            let (args, end) = match &mac.params {
+                None => (Vec::new(), token.spelling.span.end),
+                Some(_) => {
+                    // A function-like macro name without arguments is just a name
+                    match self.next_unexpanded() {
+                        Some(next) if next.is_punct(Punct::LParen) => {}
+                        next => {
+                            self.pending.extend(next);
+                            return Some(token);
+                        }
+                    }
+                    match self.collect_args(&token, &mac) {
+                        Some((args, rparen)) => (args, rparen.spelling.span.end),
+                        None => continue,
+                    }
+                }
+            };
+
+            // Nested expansions report the outermost invocation
+            let invocation = token.expansion.unwrap_or(Location {
+                file: token.spelling.file,
+                span: Span::new(token.spelling.span.start, end),
+            });
+            let mut expansion = self.substitute(&mac, &args);
+            for (i, t) in expansion.iter_mut().enumerate() {
+                t.expansion = Some(invocation);
+                t.line_start = false;
+                if i == 0 {
+                    t.space = token.space;
+                }
+                t.hide.extend(token.hide.iter().cloned());
+                t.hide.push(name.clone());
+            }
+            self.pending.extend(expansion.into_iter().rev());
+        }
+    }
+
+    // The arguments of a function-like macro invocation up to its `)`, the
+    // `(` already read
+    fn collect_args(&mut self, name: &PpToken, mac: &Macro) -> Option<(Vec<Vec<PpToken>>, PpToken)> {
+        let params = mac.params.as_deref().unwrap_or_default();
+        let mut args = vec![Vec::new()];
+        let mut depth = 0;
+        let rparen = loop {
+            let Some(token) = self.next_unexpanded() else {
+                self.error(
+                    name.spelling,
+                    format!("unterminated argument list invoking macro '{}'", name.text),
+                );
+                return None;
+            };
+            if token.is_punct(Punct::LParen) {
+                depth += 1;
+            } else if token.is_punct(Punct::RParen) {
+                if depth == 0 {
+                    break token;
+                }
+                depth -= 1;
+            } else if token.is_punct(Punct::Comma) && depth == 0 && !(mac.variadic && args.len() == params.len()) {
+                args.push(Vec::new());
+                continue;
+            }
+            args.last_mut().expect("at least one argument").push(token);
+        };
+
+        // `f()` passes no arguments to a macro without parameters, and the
+        // variable arguments may be left out entirely
+        if params.is_empty() && args.len() == 1 && args[0].is_empty() {
+            args.clear();
+        } else if mac.variadic && args.len() + 1 == params.len() {
+            args.push(Vec::new());
+        }
+        if args.len() != params.len() {
+            let message = if args.len() < params.len() {
+                format!(
+                    "macro '{}' requires {} arguments, but only {} given",
+                    name.text,
+                    params.len(),
+                    args.len()
+                )
+            } else {
+                format!("macro '{}' passed {} arguments, but takes just {}", name.text, args.len(), params.len())
+            };
+            self.error(name.spelling, message);
+            return None;
+        }
+        Some((args, rparen))
+    }
+
+    // The body of `mac` with its parameters replaced, ready to be rescanned
+    fn substitute(&mut self, mac: &Macro, args: &[Vec<PpToken>]) -> Vec<PpToken> {
+        let body = &mac.body;
+        let mut out: Vec<PpToken> = Vec::new();
+        // The last operand of `##` was an empty argument, so there is
+        // nothing to paste onto
+        let mut placemarker = false;
+        let mut i = 0;
+        while i < body.len() {
+            let token = &body[i];
+            let next = body.get(i + 1);
+
+            if token.is_punct(Punct::Hash) && mac.params.is_some() {
+                if let Some(p) = next.and_then(|next| mac.param(next)) {
+                    out.push(self.stringify(token, &args[p]));
+                    placemarker = false;
+                    i += 2;
+                    continue;
+                }
+            }
+
+            if token.is_punct(Punct::HashHash) {
+                if let Some(next) = next {
+                    let rhs = match mac.param(next) {
+                        Some(p) => args[p].clone(),
+                        None => vec![next.clone()],
+                    };
+                    let mut rhs = rhs.into_iter();
+                    match (placemarker, out.pop()) {
+                        (false, Some(lhs)) => {
+                            if let Some(first) = rhs.next() {
+                                out.extend(self.paste(lhs, first));
+                            } else {
+                                out.push(lhs);
+                            }
+                            placemarker = false;
+                        }
+                        (_, lhs) => {
+                            out.extend(lhs);
+                            placemarker = rhs.len() == 0;
+                        }
+                    }
+                    out.extend(rhs);
+                    i += 2;
+                    continue;
+                }
+            }
+
+            match mac.param(token) {
+                // Operands of `##` are pasted as written, other arguments
+                // are expanded on their own first
+                Some(p) if next.is_some_and(|next| next.is_punct(Punct::HashHash)) => {
+                    out.extend(args[p].iter().cloned());
+                    placemarker = args[p].is_empty();
+                }
+                Some(p) => {
+                    let mut arg = self.expand_isolated(args[p].clone());
+                    if let Some(first) = arg.first_mut() {
+                        first.space = token.space;
+                    }
+                    out.extend(arg);
+                    placemarker = false;
+                }
+                None => {
+                    out.push(token.clone());
+                    placemarker = false;
+                }
+            }
+            i += 1;
+        }
+        out
+    }
+
+    // Fully expand a macro argument, which cannot reach past its own tokens
+    fn expand_isolated(&mut self, tokens: Vec<PpToken>) -> Vec<PpToken> {
+        let pending = std::mem::replace(&mut self.pending, tokens.into_iter().rev().collect());
+        let isolated = std::mem::replace(&mut self.isolated, true);
+        let mut out = Vec::new();
+        while let Some(token) = self.next_expanded() {
+            out.push(token);
+        }
+        self.pending = pending;
+        self.isolated = isolated;
+        out
+    }
+
+    // `#arg`: the argument's spelling as a string literal, placed at the `#`
+    fn stringify(&mut self, hash: &PpToken, arg: &[PpToken]) -> PpToken {
+        let mut text = String::from("\"");
+        for (i, token) in arg.iter().enumerate() {
+            if i > 0 && token.space {
+                text.push(' ');
+            }
+            match token.token {
+                Token::StringLiteral { .. } | Token::CharLiteral { .. } => {
+                    for c in token.text.chars() {
+                        if c == '"' || c == '\\' {
+                            text.push('\\');
+                        }
+                        text.push(c);
+                    }
+                }
+                _ => text.push_str(&token.text),
+            }
+        }
+        text.push('"');
+
+        let token = self.relex(&text).unwrap_or_else(|| Token::Error(format!("invalid string literal {}", text)));
+        PpToken { token, text, hide: Vec::new(), ..hash.clone() }
+    }
+
+    // `lhs ## rhs`; if the two do not form a single token they stay apart
+    fn paste(&mut self, lhs: PpToken, rhs: PpToken) -> Vec<PpToken> {
+        let text = format!("{}{}", lhs.text, rhs.text);
+        match self.relex(&text) {
+            Some(token) => vec![PpToken { token, text, ..lhs }],
+            None => {
+                self.error(
+                    rhs.spelling,
+                    format!(
+                        "pasting \"{}\" and \"{}\" does not give a valid preprocessing token",
+                        lhs.text, rhs.text
+                    ),
+                );
+                vec![lhs, rhs]
+            }
+        }
+    }
+
+    // `text` as a single token, if it is one
+    fn relex(&self, text: &str) -> Option<Token> {
+        let mut tokens = Lexer::new_with_config(text, &self.config);
+        match (tokens.next(), tokens.next()) {
+            (Some(token), None) if token.span.len() == text.len() && token.token.kind() != TokenKind::Comment => {
+                Some(token.token.into_owned())
+            }
+            _ => None,
+        }
+    }
+
+    // The tokens of the directive at the reader, `#` excluded
+    fn directive_line(&mut self) -> (PpToken, Vec<PpToken>) {
+        let reader = self.stack.last_mut().expect("reading a file");
+        let tokens = &self.files[reader.file.0].tokens;
+        let hash = tokens[reader.next].clone();
+        reader.next += 1;
+        let start = reader.next;
+        while tokens.get(reader.next).is_some_and(|t| !t.line_start) {
+            reader.next += 1;
+        }
+        (hash, tokens[start..reader.next].to_vec())
+    }
+
+    fn directive(&mut self) {
+        let (hash, line) = self.directive_line();
+        // A lone `#` is a null directive
+        let Some((name, args)) = line.split_first() else {
+            return;
+        };
+
+        match name.text.as_str() {
+            "define" => self.define(name, args),
+            "undef" => {
+                if let Some(macro_name) = self.macro_name(name, args) {
+                    self.macros.remove(macro_name.text.as_str());
+                    self.extra_tokens(name, &args[1..]);
+                }
+            }
+            "include" => self.include(name, args),
+            "if" | "ifdef" | "ifndef" => {
+                let taken = self.condition(name, args);
+                let reader = self.stack.last_mut().expect("reading a file");
+                reader.conds.push(Cond { location: hash.spelling, taken, seen_else: false });
+                if !taken {
+                    self.skip_group();
+                }
+            }
+            "elif" | "elifdef" | "elifndef" => {
+                let reader = self.stack.last_mut().expect("reading a file");
+                match reader.conds.last() {
+                    None => self.error(name.spelling, format!("#{} without #if", name.text)),
+                    Some(cond) if cond.seen_else => {
+                        self.error(name.spelling, format!("#{} after #else", name.text));
+                        self.skip_group();
+                    }
+                    Some(cond) if cond.taken => self.skip_group(),
+                    Some(_) => {
+                        let taken = self.condition(name, args);
+                        let reader = self.stack.last_mut().expect("reading a file");
+                        reader.conds.last_mut().expect("checked above").taken = taken;
+                        if !taken {
+                            self.skip_group();
+                        }
+                    }
+                }
+            }
+            "else" => {
+                self.extra_tokens(name, args);
+                let reader = self.stack.last_mut().expect("reading a file");
+                match reader.conds.last_mut() {
+                    None => self.error(name.spelling, "#else without #if"),
+                    Some(cond) if cond.seen_else => {
+                        self.error(name.spelling, "#else after #else");
+                        self.skip_group();
+                    }
+                    Some(cond) => {
+                        cond.seen_else = true;
+                        let taken = std::mem::replace(&mut cond.taken, true);
+                        if taken {
+                            self.skip_group();
+                        }
+                    }
+                }
+            }
+            "endif" => {
+                self.extra_tokens(name, args);
+                let reader = self.stack.last_mut().expect("reading a file");
+                if reader.conds.pop().is_none() {
+                    self.error(name.spelling, "#endif without #if");
+                }
+            }
+            "error" => {
+                let message: Vec<_> = args.iter().map(|t| t.text.as_str()).collect();
+                self.error(name.spelling, format!("#error {}", message.join(" ")));
+            }
+            // Nothing for the parser to act on
+            "pragma" | "line" | "warning" => {}
+            _ => self.error(name.spelling, format!("invalid preprocessing directive #{}", name.text)),
+        }
+    }
+
+    // Skip a group whose condition is false, up to the `#elif`, `#else` or
+    // `#endif` that ends it
+    fn skip_group(&mut self) {
+        let reader = self.stack.last_mut().expect("reading a file");
+        let tokens = &self.files[reader.file.0].tokens;
+        let mut depth = 0;
+        while let Some(token) = tokens.get(reader.next) {
+            let name = tokens.get(reader.next + 1).filter(|name| !name.line_start);
+            if token.line_start && token.is_punct(Punct::Hash) {
+                match name.map(|name| name.text.as_str()) {
+                    Some("if" | "ifdef" | "ifndef") => depth += 1,
+                    Some("elif" | "elifdef" | "elifndef" | "else") if depth == 0 => return,
+                    Some("endif") if depth == 0 => return,
+                    Some("endif") => depth -= 1,
+                    _ => {}
+                }
+            }
+            reader.next += 1;
+        }
+    }
+
+    fn macro_name<'a>(&mut self, directive: &PpToken, args: &'a [PpToken]) -> Option<&'a PpToken> {
+        match args.first() {
+            Some(name) if name.is("defined") => {
+                self.error(name.spelling, "\"defined\" cannot be used as a macro name");
+                None
+            }
+            Some(name) if name.ident().is_some() => Some(name),
+            Some(other) => {
+                self.error(other.spelling, "macro names must be identifiers");
+                None
+            }
+            None => {
+                self.error(directive.spelling, format!("no macro name given in #{} directive", directive.text));
+                None
+            }
+        }
+    }
+
+    fn extra_tokens(&mut self, directive: &PpToken, extra: &[PpToken]) {
+        if let Some(first) = extra.first() {
+            self.error(first.spelling, format!("extra tokens at end of #{} directive", directive.text));
+        }
+    }
+
+    fn define(&mut self, directive: &PpToken, args: &[PpToken]) {
+        let Some(name) = self.macro_name(directive, args) else {
+            return;
+        };
+        let mut rest = &args[1..];
+
+        // Function-like if the `(` follows the name right away
+        let mut params = None;
+        let mut variadic = false;
+        if rest.first().is_some_and(|t| t.is_punct(Punct::LParen) && !t.space) {
+            let mut names = Vec::new();
+            let mut i = 1;
+            loop {
+                match rest.get(i) {
+                    Some(t) if t.is_punct(Punct::RParen) && names.is_empty() => break,
+                    Some(t) if t.is_punct(Punct::Ellipsis) => {
+                        names.push("__VA_ARGS__".to_string());
+                        variadic = true;
+                        i += 1;
+                    }
+                    Some(t) if t.ident().is_some() => {
+                        names.push(t.text.clone());
+                        i += 1;
+                    }
+                    Some(t) => return self.error(t.spelling, "expected parameter name"),
+                    None => return self.error(name.spelling, "missing ')' in macro parameter list"),
+                }
+                match rest.get(i) {
+                    Some(t) if t.is_punct(Punct::RParen) => break,
+                    Some(t) if t.is_punct(Punct::Comma) && !variadic => i += 1,
+                    Some(t) => return self.error(t.spelling, "expected ',' or ')' in macro parameter list"),
+                    None => return self.error(name.spelling, "missing ')' in macro parameter list"),
+                }
+            }
+            rest = &rest[i + 1..];
+            params = Some(names);
+        }
+
+        let mac = Macro { params, variadic, body: rest.to_vec() };
+        if let (Some(first), Some(last)) = (mac.body.first(), mac.body.last()) {
+            if first.is_punct(Punct::HashHash) || last.is_punct(Punct::HashHash) {
+                let at = if first.is_punct(Punct::HashHash) { first } else { last };
+                return self.error(at.spelling, "'##' cannot appear at either end of a macro expansion");
+            }
+        }
+        if mac.params.is_some() {
+            for (i, token) in mac.body.iter().enumerate() {
+                if token.is_punct(Punct::Hash) && mac.body.get(i + 1).and_then(|next| mac.param(next)).is_none() {
+                    return self.error(token.spelling, "'#' is not followed by a macro parameter");
+                }
+            }
+        }
+
+        match self.macros.get(name.text.as_str()) {
+            Some(old) if !old.same_as(&mac) => self.error(name.spelling, format!("'{}' redefined", name.text)),
+            _ => {}
+        }
+        self.macros.insert(name.text.as_str().into(), Rc::new(mac));
+    }
+
+    fn include(&mut self, directive: &PpToken, args: &[PpToken]) {
+        // Macros may spell the header name too
+        let expanded;
+        let mut args = args;
+        if args.first().is_some_and(|t| t.ident().is_some()) {
+            expanded = self.expand_isolated(args.to_vec());
+            args = &expanded;
+        }
+
+        let (header, quoted) = match args {
+            [PpToken { token: Token::StringLiteral { raw, .. }, .. }, extra @ ..] => {
+                self.extra_tokens(directive, extra);
+                (raw.clone(), true)
+            }
+            [open, ..] if open.is_punct(Punct::Lt) => {
+                match args.iter().position(|t| t.is_punct(Punct::Gt)) {
+                    // The name is spelled as in the source, spaces and all
+                    Some(close) => {
+                        let header = match args[1..close].first() {
+                            Some(first) if first.spelling.file == args[close].spelling.file && first.expansion.is_none() => {
+                                let range = first.spelling.span.start.offset..args[close].spelling.span.start.offset;
+                                self.files[first.spelling.file.0].text[range].to_string()
+                            }
+                            _ => args[1..close].iter().map(|t| t.text.as_str()).collect(),
+                        };
+                        self.extra_tokens(directive, &args[close + 1..]);
+                        (header, false)
+                    }
+                    None => return self.error(open.spelling, "missing terminating > character"),
+                }
+            }
+            _ => return self.error(directive.spelling, "#include expects \"FILENAME\" or <FILENAME>"),
+        };
+        let at = args[0].spelling;
+
+        if self.stack.len() > MAX_INCLUDE_DEPTH {
+            return self.error(at, "#include nested too deeply");
+        }
+        match self.resolve(&header, quoted) {
+            Some(file) => self.stack.push(Reader { file, next: 0, conds: Vec::new() }),
+            None => self.error(at, format!("'{}' file not found", header)),
+        }
+    }
+
+    // `"name"` is looked for next to the including file first, then like
+    // `<name>` among the virtual files and the include directories
+    fn resolve(&mut self, header: &str, quoted: bool) -> Option<FileId> {
+        let current = self.stack.last().expect("reading a file").file;
+        let here = self.files[current.0].path.as_ref().and_then(|path| path.parent()).map(Path::to_path_buf);
+        if quoted {
+            if let Some(dir) = here {
+                if let Ok(file) = self.load(&dir.join(header)) {
+                    return Some(file);
+                }
+            }
+        }
+        if let Some(&file) = self.virtual_files.get(header) {
+            return Some(file);
+        }
+        let dirs = self.include_dirs.clone();
+        dirs.iter().find_map(|dir| self.load(&dir.join(header)).ok())
+    }
+
+    // Evaluate the condition of `#if`, `#ifdef` and their kin
+    fn condition(&mut self, directive: &PpToken, args: &[PpToken]) -> bool {
+        match directive.text.as_str() {
+            "ifdef" | "ifndef" | "elifdef" | "elifndef" => {
+                let Some(name) = self.macro_name(directive, args) else {
+                    return false;
+                };
+                let defined = self.macros.contains_key(name.text.as_str());
+                self.extra_tokens(directive, &args[1..]);
+                defined != directive.text.ends_with("ndef")
+            }
+            _ => {
+                let tokens = self.replace_defined(args);
+                let tokens = self.expand_isolated(tokens);
+                let end = args.last().unwrap_or(directive).spelling;
+                match Eval::new(&tokens, end).run() {
+                    Ok(value) => value.bits != 0,
+                    Err(err) => {
+                        self.errors.push(err);
+                        false
+                    }
+                }
+            }
+        }
+    }
+
+    // `defined X` and `defined(X)` become 1 or 0 before the macros in an
+    // `#if` are expanded
+    fn replace_defined(&mut self, args: &[PpToken]) -> Vec<PpToken> {
+        let mut out = Vec::new();
+        let mut i = 0;
+        while i < args.len() {
+            let token = &args[i];
+            if !token.is("defined") {
+                out.push(token.clone());
+                i += 1;
+                continue;
+            }
+            let (name, len) = match (args.get(i + 1), args.get(i + 2), args.get(i + 3)) {
+                (Some(name), ..) if name.ident().is_some() => (Some(name), 2),
+                (Some(open), Some(name), Some(close))
+                    if open.is_punct(Punct::LParen) && name.ident().is_some() && close.is_punct(Punct::RParen) =>
+                {
+                    (Some(name), 4)
+                }
+                _ => (None, 1),
+            };
+            match name {
+                Some(name) => {
+                    let defined = self.macros.contains_key(name.text.as_str());
+                    out.push(number(token, defined as u64));
+                }
+                None => {
+                    self.error(token.spelling, "operator \"defined\" requires an identifier");
+                    out.push(number(token, 0));
+                }
+            }
+            i += len;
+        }
+        out
+    }
+}
+
+// Lex a whole file and note where directives can start. Token texts are
+// taken after line splicing, so `#` and `##` work on what the lexer saw.
+fn lex(config: &LexerConfig, file: FileId, text: &str) -> Vec<PpToken> {
+    let mut tokens = Vec::new();
+    let mut end = 0;
+    let (mut space, mut line_start) = (false, true);
+    for token in Lexer::new_with_config(text, config) {
+        let gap = translate(&text[end..token.span.start.offset], config.trigraphs());
+        space |= !gap.is_empty();
+        line_start |= gap.contains('\n');
+        end = token.span.end.offset;
+        if let Token::Comment(_) = token.token {
+            space = true;
+            continue;
+        }
+        let spelling = translate(token.span.text(text), config.trigraphs()).into_owned();
+        tokens.push(PpToken {
+            token: match token.token {
+                Token::Identifier(_) => Token::Identifier(spelling.clone()),
+                other => other.into_owned(),
+            },
+            text: spelling,
+            spelling: Location { file, span: token.span },
+            expansion: None,
+            space,
+            line_start,
+            hide: Vec::new(),
+        });
+        space = false;
+        line_start = false;
+    }
+    tokens
+}
+
+// An integer token standing in for `at`
+fn number(at: &PpToken, value: u64) -> PpToken {
+    let literal = value.to_string();
+    PpToken {
+        token: Token::Number {
+            literal: literal.clone(),
+            raw: literal.clone(),
+            kind: NumberKind::Integer,
+            base: NumberBase::Decimal,
+            suffix: None,
+        },
+        text: literal,
+        ..at.clone()
+    }
+}
+
+// `#if` arithmetic is done in intmax_t or uintmax_t
+#[derive(Debug, Clone, Copy)]
+struct Value {
+    bits: u64,
+    unsigned: bool,
+}
+
+impl Value {
+    fn signed(bits: u64) -> Self {
+        Value { bits, unsigned: false }
+    }
+
+    fn truth(b: bool) -> Self {
+        Value::signed(b as u64)
+    }
+}
+
+// Evaluator for the constant expression of an `#if`, with macros already
+// expanded
+struct Eval<'a> {
+    tokens: &'a [PpToken],
+    pos: usize,
+    // Where errors at the end of the expression go
+    end: Location,
+}
+
+impl<'a> Eval<'a> {
+    fn new(tokens: &'a [PpToken], end: Location) -> Self {
+        Eval { tokens, pos: 0, end }
+    }
+
+    fn run(mut self) -> Result<Value, PreprocessError> {
+        let value = self.conditional(true)?;
+        match self.tokens.get(self.pos) {
+            None => Ok(value),
+            Some(token) => Err(error(token.spelling, format!("missing binary operator before token \"{}\"", token.text))),
+        }
+    }
+
+    fn peek_punct(&self) -> Option<Punct> {
+        match self.tokens.get(self.pos).map(|t| &t.token) {
+            Some(Token::Symbol(punct)) => Some(*punct),
+            _ => None,
+        }
+    }
+
+    fn location(&self) -> Location {
+        self.tokens.get(self.pos).map_or(self.end, |t| t.spelling)
+    }
+
+    fn expect(&mut self, punct: Punct) -> Result<(), PreprocessError> {
+        if self.peek_punct() == Some(punct) {
+            self.pos += 1;
+            Ok(())
+        } else {
+            Err(error(self.location(), format!("expected '{}' in preprocessor expression", punct)))
+        }
+    }
+
+    // Errors like division by zero only count where `live`, outside the
+    // branch a `&&`, `||` or `?:` skips
+    fn conditional(&mut self, live: bool) -> Result<Value, PreprocessError> {
+        let cond = self.binary(0, live)?;
+        if self.peek_punct() != Some(Punct::Question) {
+            return Ok(cond);
+        }
+        self.pos += 1;
+        let then = self.conditional(live && cond.bits != 0)?;
+        self.expect(Punct::Colon)?;
+        let otherwise = self.conditional(live && cond.bits == 0)?;
+        let value = if cond.bits != 0 { then } else { otherwise };
+        Ok(Value { bits: value.bits, unsigned: then.unsigned || otherwise.unsigned })
+    }
+
+    fn binary(&mut self, min_prec: u8, live: bool) -> Result<Value, PreprocessError> {
+        let mut lhs = self.unary(live)?;
+        while let Some((punct, prec)) = self.peek_punct().and_then(|p| precedence(p).map(|prec| (p, prec))) {
+            if prec < min_prec {
+                break;
+            }
+            let at = self.location();
+            self.pos += 1;
+            let rhs_live = match punct {
+                Punct::AmpAmp => live && lhs.bits != 0,
+                Punct::PipePipe => live && lhs.bits == 0,
+                _ => live,
+            };
+            let rhs = self.binary(prec + 1, rhs_live)?;
+            lhs = apply(punct, lhs, rhs, live, at)?;
+        }
+        Ok(lhs)
+    }
+
+    fn unary(&mut self, live: bool) -> Result<Value, PreprocessError> {
+        let Some(token) = self.tokens.get(self.pos) else {
+            return Err(error(self.end, "expected value in expression"));
+        };
+        self.pos += 1;
+        match &token.token {
+            Token::Symbol(Punct::Plus) => self.unary(live),
+            Token::Symbol(Punct::Minus) => {
+                let value = self.unary(live)?;
+                Ok(Value { bits: value.bits.wrapping_neg(), ..value })
+            }
+            Token::Symbol(Punct::Tilde) => {
+                let value = self.unary(live)?;
+                Ok(Value { bits: !value.bits, ..value })
+            }
+            Token::Symbol(Punct::Bang) => Ok(Value::truth(self.unary(live)?.bits == 0)),
+            Token::Symbol(Punct::LParen) => {
+                let value = self.conditional(live)?;
+                self.expect(Punct::RParen)?;
+                Ok(value)
+            }
+            Token::Number { .. } => match token.token.number_value() {
+                Some(Ok(Constant::Integer { value, ty })) => Ok(Value { bits: value, unsigned: ty.is_unsigned() }),
+                Some(Ok(Constant::Floating { .. })) => {
+                    Err(error(token.spelling, "floating constant in preprocessor expression"))
+                }
+                Some(Err(err)) => Err(error(token.spelling, err.to_string())),
+                None => unreachable!("a number token"),
+            },
+            Token::CharLiteral { value, .. } => Ok(Value::signed(*value as u64)),
+            Token::Keyword(Keyword::True) => Ok(Value::truth(true)),
+            // Identifiers left after expansion are 0
+            Token::Identifier(_) | Token::Keyword(_) => Ok(Value::truth(false)),
+            _ => Err(error(
+                token.spelling,
+                format!("token \"{}\" is not valid in preprocessor expressions", token.text),
+            )),
+        }
+    }
+}
+
+// Binding strength of the binary operators, loosest first
+fn precedence(punct: Punct) -> Option<u8> {
+    Some(match punct {
+        Punct::PipePipe                                  => 0,
+        Punct::AmpAmp                                    => 1,
+        Punct::Pipe                                      => 2,
+        Punct::Caret                                     => 3,
+        Punct::Amp                                       => 4,
+        Punct::EqEq | Punct::Ne                          => 5,
+        Punct::Lt | Punct::Gt | Punct::Le | Punct::Ge    => 6,
+        Punct::Shl | Punct::Shr                          => 7,
+        Punct::Plus | Punct::Minus                       => 8,
+        Punct::Star | Punct::Slash | Punct::Percent      => 9,
+        _ => return None,
+    })
+}
+
+fn apply(punct: Punct, lhs: Value, rhs: Value, live: bool, at: Location) -> Result<Value, PreprocessError> {
+    // The usual arithmetic conversions, shifts keep the left operand's type
+    let unsigned = lhs.unsigned || rhs.unsigned;
+    let (a, b) = (lhs.bits, rhs.bits);
+    let less = |a: u64, b: u64| if unsigned { a < b } else { (a as i64) < (b as i64) };
+    let value = |bits| Value { bits, unsigned };
+    Ok(match punct {
+        Punct::PipePipe => Value::truth(a != 0 || b != 0),
+        Punct::AmpAmp   => Value::truth(a != 0 && b != 0),
+        Punct::Pipe     => value(a | b),
+        Punct::Caret    => value(a ^ b),
+        Punct::Amp      => value(a & b),
+        Punct::EqEq     => Value::truth(a == b),
+        Punct::Ne       => Value::truth(a != b),
+        Punct::Lt       => Value::truth(less(a, b)),
+        Punct::Gt       => Value::truth(less(b, a)),
+        Punct::Le       => Value::truth(!less(b, a)),
+        Punct::Ge       => Value::truth(!less(a, b)),
+        Punct::Shl      => Value { bits: a.wrapping_shl(b as u32), ..lhs },
+        Punct::Shr if lhs.unsigned => Value { bits: a.wrapping_shr(b as u32), ..lhs },
+        Punct::Shr      => Value { bits: (a as i64).wrapping_shr(b as u32) as u64, ..lhs },
+        Punct::Plus     => value(a.wrapping_add(b)),
+        Punct::Minus    => value(a.wrapping_sub(b)),
+        Punct::Star     => value(a.wrapping_mul(b)),
+        Punct::Slash | Punct::Percent if b == 0 => {
+            if live {
+                return Err(error(at, "division by zero in #if"));
+            }
+            value(0)
+        }
+        Punct::Slash if unsigned => value(a / b),
+        Punct::Slash    => value((a as i64).wrapping_div(b as i64) as u64),
+        Punct::Percent if unsigned => value(a % b),
+        Punct::Percent  => value((a as i64).wrapping_rem(b as i64) as u64),
+        _ => unreachable!("not a binary operator: {}", punct),
+    })
+}
+
+fn error(location: Location, message: impl Into<String>) -> PreprocessError {
+    PreprocessError { message: message.into(), location }
+}
@@ -0,0 +1,121 @@
+use std::collections::VecDeque;
+use std::iter::Fuse;
+
+use crate::token::{Position, PositionedToken, Span, Token};
+
+// Most tokens the parser ever looks ahead, the current one included
+pub const LOOKAHEAD: usize = 2;
+
+/// The parser's view of the token stream. Tokens are pulled lazily into a
+/// bounded lookahead buffer; comments are set aside until taken instead of
+/// reaching the grammar, and once the input runs out a synthetic
+/// `Token::Eof` is returned forever.
+pub struct TokenSource<S, I: Iterator<Item = PositionedToken<S>>> {
+    // Fused, as `tokens` is not asked again once it has run out
+    tokens: Fuse<I>,
+    // Tokens pulled from `tokens` but not consumed yet, front is the current one
+    lookahead: VecDeque<PositionedToken<S>>,
+    comments: Vec<PositionedToken<S>>,
+    keep_comments: bool,
+    // End of the last token read, where `Eof` is placed
+    end: Position,
+    consumed: usize,
+}
+
+impl<S, I> TokenSource<S, I>
+where
+    S: AsRef<str>,
+    I: Iterator<Item = PositionedToken<S>>,
+{
+    pub fn new(tokens: impl IntoIterator<IntoIter = I>) -> Self {
+        let mut source = Self {
+            tokens: tokens.into_iter().fuse(),
+            lookahead: VecDeque::with_capacity(LOOKAHEAD),
+            comments: Vec::new(),
+            keep_comments: true,
+            end: Position { offset: 0, row: 1, column: 1 },
+            consumed: 0,
+        };
+        source.fill(1);
+        source
+    }
+
+    /// Keep skipped comments until they are taken, the default, or drop
+    /// them as they are skipped.
+    pub fn with_comments(mut self, keep: bool) -> Self {
+        self.keep_comments = keep;
+        if !keep {
+            self.comments.clear();
+        }
+        self
+    }
+
+    // Make sure `n` tokens are buffered, padding with `Eof` past the end
+    fn fill(&mut self, n: usize) {
+        debug_assert!(n <= LOOKAHEAD);
+        while self.lookahead.len() < n {
+            let token = loop {
+                match self.tokens.next() {
+                    Some(token) => {
+                        self.end = token.span.end;
+                        if let Token::Comment(_) = token.token {
+                            if self.keep_comments {
+                                self.comments.push(token);
+                            }
+                        } else {
+                            break token;
+                        }
+                    }
+                    None => {
+                        break PositionedToken {
+                            token: Token::Eof,
+                            span: Span::new(self.end, self.end),
+                        }
+                    }
+                }
+            };
+            self.lookahead.push_back(token);
+        }
+    }
+
+    /// The current token.
+    pub fn peek(&self) -> &PositionedToken<S> {
+        &self.lookahead[0]
+    }
+
+    /// The token `n` places after the current one, `n < LOOKAHEAD`.
+    pub fn peek_nth(&mut self, n: usize) -> &PositionedToken<S> {
+        self.fill(n + 1);
+        &self.lookahead[n]
+    }
+
+    pub fn at_end(&self) -> bool {
+        matches!(self.peek().token, Token::Eof)
+    }
+
+    /// Consume the current token. At the end of input this keeps returning
+    /// `Eof`.
+    pub fn bump(&mut self) -> PositionedToken<S> {
+        let token = self.lookahead.pop_front().expect("lookahead is never empty");
+        if !matches!(token.token, Token::Eof) {
+            self.consumed += 1;
+        }
+        self.fill(1);
+        token
+    }
+
+    /// Number of tokens consumed so far, comments excluded.
+    pub fn consumed(&self) -> usize {
+        self.consumed
+    }
+
+    /// Comments skipped and not taken yet, in source order.
+    pub fn comments(&self) -> &[PositionedToken<S>] {
+        &self.comments
+    }
+
+    /// Take the comments skipped so far, leaving none behind.
+    pub fn take_comments(&mut self) -> Vec<PositionedToken<S>> {
+        std::mem::take(&mut self.comments)
+    }
+}
@@ -1,53 +1,178 @@
-#[derive(Debug, PartialEq, Eq)]
-pub enum Token {
-    Keyword(String),
-    Symbol(String),
-    Identifier(String),
+use std::fmt;
+
+// `S` holds the token text: `String` for owned tokens, `&str` for tokens
+// borrowed from the source by the lexer
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Token<S = String> {
+    Keyword(Keyword),
+    Symbol(Punct),
+    Identifier(S),
+    // `literal` is the number without its suffix, `raw` the whole spelling
     Number {
-        literal: String,
+        literal: S,
+        raw: S,
         kind: NumberKind,
         base: NumberBase,
         suffix: Option<NumberSuffix>,
     },
-    CharLiteral(String),
-    StringLiteral(String),
-    Comment(String),
+    // `raw` is the spelling between the quotes, `value` the decoded content:
+    // the character's value for char literals, UTF-8 bytes for strings
+    CharLiteral { raw: S, value: u32 },
+    StringLiteral { raw: S, value: Vec<u8> },
+    Comment(S),
     Error(String),
+    // End of input, never produced by the lexer itself
+    Eof,
 }
 
-impl Token {
+pub type TokenRef<'src> = Token<&'src str>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TokenKind {
+    Keyword,
+    Symbol,
+    Identifier,
+    Number,
+    CharLiteral,
+    StringLiteral,
+    Comment,
+    Error,
+    Eof,
+}
+
+impl fmt::Display for TokenKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self, f)
+    }
+}
+
+impl<S: AsRef<str>> Token<S> {
     pub fn raw(&self) -> String {
         match self {
-            Token::Keyword(s)             => s.to_string(),
-            Token::Symbol(s)              => s.to_string(),
-            Token::Identifier(s)          => s.to_string(),
-            Token::Number { literal, .. } => literal.to_string(),
-            Token::CharLiteral(s)         => s.to_string(),
-            Token::StringLiteral(s)       => s.to_string(),
-            Token::Comment(s)             => s.to_string(),
-            Token::Error(s)               => s.to_string(),
+            Token::Keyword(k)                => k.as_str().to_string(),
+            Token::Symbol(p)                 => p.as_str().to_string(),
+            Token::Identifier(s)             => s.as_ref().to_string(),
+            Token::Number { raw, .. }        => raw.as_ref().to_string(),
+            Token::CharLiteral { raw, .. }   => raw.as_ref().to_string(),
+            Token::StringLiteral { raw, .. } => raw.as_ref().to_string(),
+            Token::Comment(s)                => s.as_ref().to_string(),
+            Token::Error(s)                  => s.to_string(),
+            Token::Eof                       => String::new(),
         }
     }
 
-    pub fn kind(&self) -> String {
+    pub fn kind(&self) -> TokenKind {
         match self {
-            Token::Keyword(_)       => "Keyword".to_string(),
-            Token::Symbol(_)        => "Symbol".to_string(),
-            Token::Identifier(_)    => "Identifier".to_string(),
-            Token::Number { .. }    => "Number".to_string(),
-            Token::CharLiteral(_)   => "CharLiteral".to_string(),
-            Token::StringLiteral(_) => "StringLiteral".to_string(),
-            Token::Comment(_)       => "Comment".to_string(),
-            Token::Error(_)         => "Error".to_string(),
+            Token::Keyword(_)           => TokenKind::Keyword,
+            Token::Symbol(_)            => TokenKind::Symbol,
+            Token::Identifier(_)        => TokenKind::Identifier,
+            Token::Number { .. }        => TokenKind::Number,
+            Token::CharLiteral { .. }   => TokenKind::CharLiteral,
+            Token::StringLiteral { .. } => TokenKind::StringLiteral,
+            Token::Comment(_)           => TokenKind::Comment,
+            Token::Error(_)             => TokenKind::Error,
+            Token::Eof                  => TokenKind::Eof,
+        }
+    }
+
+    pub fn into_owned(self) -> Token {
+        match self {
+            Token::Keyword(k)       => Token::Keyword(k),
+            Token::Symbol(p)        => Token::Symbol(p),
+            Token::Identifier(s)    => Token::Identifier(s.as_ref().to_string()),
+            Token::Number { literal, raw, kind, base, suffix } => Token::Number {
+                literal: literal.as_ref().to_string(),
+                raw: raw.as_ref().to_string(),
+                kind,
+                base,
+                suffix,
+            },
+            Token::CharLiteral { raw, value } => Token::CharLiteral {
+                raw: raw.as_ref().to_string(),
+                value,
+            },
+            Token::StringLiteral { raw, value } => Token::StringLiteral {
+                raw: raw.as_ref().to_string(),
+                value,
+            },
+            Token::Comment(s)       => Token::Comment(s.as_ref().to_string()),
+            Token::Error(s)         => Token::Error(s),
+            Token::Eof              => Token::Eof,
         }
     }
 }
 
-#[derive(Debug)]
-pub struct PositionedToken {
-    pub token: Token,
+// Prints the token the way it is spelled in source. Comments come back as
+// block comments and errors as their message.
+impl<S: AsRef<str>> fmt::Display for Token<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Token::Keyword(k)                => write!(f, "{}", k),
+            Token::Symbol(p)                 => write!(f, "{}", p),
+            Token::Identifier(s)             => f.write_str(s.as_ref()),
+            Token::Number { raw, .. }        => f.write_str(raw.as_ref()),
+            Token::CharLiteral { raw, .. }   => write!(f, "'{}'", raw.as_ref()),
+            Token::StringLiteral { raw, .. } => write!(f, "\"{}\"", raw.as_ref()),
+            Token::Comment(s)                => write!(f, "/* {} */", s.as_ref()),
+            Token::Error(s)                  => f.write_str(s),
+            Token::Eof                       => Ok(()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Position {
+    pub offset: usize, // byte offset into the source
     pub row: usize,
-    pub column: usize,
+    pub column: usize, // counted in chars
+}
+
+// A half-open range of source text, `end` points just past the last char
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Span {
+    pub start: Position,
+    pub end: Position,
+}
+
+impl Span {
+    pub fn new(start: Position, end: Position) -> Self {
+        Span { start, end }
+    }
+
+    pub fn range(&self) -> std::ops::Range<usize> {
+        self.start.offset..self.end.offset
+    }
+
+    pub fn len(&self) -> usize {
+        self.end.offset - self.start.offset
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn text<'a>(&self, source: &'a str) -> &'a str {
+        &source[self.range()]
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PositionedToken<S = String> {
+    pub token: Token<S>,
+    pub span: Span,
+}
+
+impl<S: AsRef<str>> PositionedToken<S> {
+    pub fn position(&self) -> Position {
+        self.span.start
+    }
+
+    pub fn into_owned(self) -> PositionedToken {
+        PositionedToken {
+            token: self.token.into_owned(),
+            span: self.span,
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -56,13 +181,13 @@ pub struct TokenLine {
     pub tokens: Vec<PositionedToken>,
 }
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum NumberKind {
     Integer,
     FloatingPoint,
 }
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum NumberBase {
     Binary,
     Decimal,
@@ -70,83 +195,248 @@ pub enum NumberBase {
     Hexadecimal,
 }
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum NumberSuffix {
     Integer(IntegerSuffix),
     FloatingPoint(FloatingPointSuffix),
 }
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct IntegerSuffix {
     unsigned: bool,
     long: u8, // 0 for none, 1 for 'L', 2 for 'LL'
+    bit_precise: bool, // C23 'wb'
 }
 
 impl IntegerSuffix {
     pub fn new(unsigned: bool, long: u8) -> Self {
-        IntegerSuffix { unsigned, long }
+        IntegerSuffix { unsigned, long, bit_precise: false }
+    }
+
+    pub fn bit_precise(unsigned: bool) -> Self {
+        IntegerSuffix { unsigned, long: 0, bit_precise: true }
+    }
+
+    pub fn is_unsigned(&self) -> bool {
+        self.unsigned
+    }
+
+    // 0 for none, 1 for 'L', 2 for 'LL'
+    pub fn long(&self) -> u8 {
+        self.long
+    }
+
+    pub fn is_bit_precise(&self) -> bool {
+        self.bit_precise
+    }
+}
+
+// The canonical lowercase spelling: `ULL` and `llu` both print as `ull`.
+// The suffix as written is part of the number token's `raw`.
+impl fmt::Display for NumberSuffix {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NumberSuffix::Integer(IntegerSuffix { unsigned, long, bit_precise }) => {
+                if *unsigned {
+                    f.write_str("u")?;
+                }
+                if *bit_precise {
+                    f.write_str("wb")?;
+                }
+                f.write_str(&"l".repeat(*long as usize))
+            }
+            NumberSuffix::FloatingPoint(FloatingPointSuffix::Float) => f.write_str("f"),
+            NumberSuffix::FloatingPoint(FloatingPointSuffix::LongDouble) => f.write_str("l"),
+        }
     }
 }
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum FloatingPointSuffix {
     Float,        // 'f' or 'F'
     LongDouble,   // 'l' or 'L'
 }
 
-pub const KEYWORDS: &[&str] = &[
-    "if",
-    "else",
-    "while",
-    "do",
-    "main",
-    "int",
-    "float",
-    "double", 
-    "char",
-    "unsigned",
-    "long",
-    "return",
-    "const",
-    "void",
-    "switch",
-    "case",
-    "continue",
-    "break",
-    "enum",
-    "struct",
-    "static",
-    "for",
-    "typedef",
-];
+// Defines a spelling enum together with its table of spellings, so the
+// two cannot drift apart
+macro_rules! token_table {
+    ($name:ident, $table:ident { $($variant:ident => $text:literal,)* }) => {
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+        pub enum $name {
+            $($variant,)*
+            /// A spelling added by a `LexerConfig` on top of the built-in table.
+            /// It is `&'static str` so that the enum stays `Copy`; a spelling
+            /// only known at run time has to be leaked, see `String::leak`.
+            Extension(&'static str),
+        }
+
+        pub const $table: &[&str] = &[$($text,)*];
+
+        impl $name {
+            // Built-in entries only, extensions belong to a `LexerConfig`
+            pub const ALL: &'static [$name] = &[$($name::$variant,)*];
+
+            pub fn as_str(&self) -> &'static str {
+                match self {
+                    $($name::$variant => $text,)*
+                    $name::Extension(text) => text,
+                }
+            }
+
+            /// Position in `ALL`, `None` for extensions.
+            pub fn index(&self) -> Option<usize> {
+                #[allow(non_camel_case_types)]
+                enum Index {
+                    $($variant,)*
+                }
+                match self {
+                    $($name::$variant => Some(Index::$variant as usize),)*
+                    $name::Extension(_) => None,
+                }
+            }
 
-pub const SYMBOLS: &[&str] = &[
-    "+",
-    "-",
-    "*",
-    "/",
-    "=",
-    "<",
-    ">",
-    "!",
-    "==",
-    "!=",
-    "&&",
-    "||",
-    "<=",
-    ">=",
-    "{",
-    "}",
-    "[",
-    "]",
-    "(",
-    ")",
-    ";",
-    ",",
-    ".",
-    ":",
-    "&",
-    "|",
-    "^",
-    "~",
-];
\ No newline at end of file
+            pub fn from_spelling(s: &str) -> Option<Self> {
+                match s {
+                    $($text => Some($name::$variant),)*
+                    _ => None,
+                }
+            }
+        }
+
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str(self.as_str())
+            }
+        }
+    };
+}
+
+// Every keyword of C89 through C23; which of them are reserved is up to
+// the `LexerConfig`
+token_table!(Keyword, KEYWORDS {
+    Auto          => "auto",
+    Break         => "break",
+    Case          => "case",
+    Char          => "char",
+    Const         => "const",
+    Continue      => "continue",
+    Default       => "default",
+    Do            => "do",
+    Double        => "double",
+    Else          => "else",
+    Enum          => "enum",
+    Extern        => "extern",
+    Float         => "float",
+    For           => "for",
+    Goto          => "goto",
+    If            => "if",
+    Int           => "int",
+    Long          => "long",
+    Register      => "register",
+    Return        => "return",
+    Short         => "short",
+    Signed        => "signed",
+    Sizeof        => "sizeof",
+    Static        => "static",
+    Struct        => "struct",
+    Switch        => "switch",
+    Typedef       => "typedef",
+    Union         => "union",
+    Unsigned      => "unsigned",
+    Void          => "void",
+    Volatile      => "volatile",
+    While         => "while",
+    // C99
+    Inline        => "inline",
+    Restrict      => "restrict",
+    _Bool         => "_Bool",
+    _Complex      => "_Complex",
+    _Imaginary    => "_Imaginary",
+    // C11
+    _Alignas      => "_Alignas",
+    _Alignof      => "_Alignof",
+    _Atomic       => "_Atomic",
+    _Generic      => "_Generic",
+    _Noreturn     => "_Noreturn",
+    _StaticAssert => "_Static_assert",
+    _ThreadLocal  => "_Thread_local",
+    // C23
+    Alignas       => "alignas",
+    Alignof       => "alignof",
+    Bool          => "bool",
+    Constexpr     => "constexpr",
+    False         => "false",
+    Nullptr       => "nullptr",
+    StaticAssert  => "static_assert",
+    ThreadLocal   => "thread_local",
+    True          => "true",
+    Typeof        => "typeof",
+    TypeofUnqual  => "typeof_unqual",
+    _BitInt       => "_BitInt",
+    _Decimal32    => "_Decimal32",
+    _Decimal64    => "_Decimal64",
+    _Decimal128   => "_Decimal128",
+});
+
+// The C17 punctuators (6.4.6), digraphs aside
+token_table!(Punct, SYMBOLS {
+    LBracket   => "[",
+    RBracket   => "]",
+    LParen     => "(",
+    RParen     => ")",
+    LBrace     => "{",
+    RBrace     => "}",
+    Dot        => ".",
+    Arrow      => "->",
+    PlusPlus   => "++",
+    MinusMinus => "--",
+    Amp        => "&",
+    Star       => "*",
+    Plus       => "+",
+    Minus      => "-",
+    Tilde      => "~",
+    Bang       => "!",
+    Slash      => "/",
+    Percent    => "%",
+    Shl        => "<<",
+    Shr        => ">>",
+    Lt         => "<",
+    Gt         => ">",
+    Le         => "<=",
+    Ge         => ">=",
+    EqEq       => "==",
+    Ne         => "!=",
+    Caret      => "^",
+    Pipe       => "|",
+    AmpAmp     => "&&",
+    PipePipe   => "||",
+    Question   => "?",
+    Colon      => ":",
+    Semi       => ";",
+    Ellipsis   => "...",
+    Eq         => "=",
+    StarEq     => "*=",
+    SlashEq    => "/=",
+    PercentEq  => "%=",
+    PlusEq     => "+=",
+    MinusEq    => "-=",
+    ShlEq      => "<<=",
+    ShrEq      => ">>=",
+    AmpEq      => "&=",
+    CaretEq    => "^=",
+    PipeEq     => "|=",
+    Comma      => ",",
+    Hash       => "#",
+    HashHash   => "##",
+});
+
+/// The digraphs (C17 6.4.6p3) and the punctuators they spell. The lexer
+/// yields the punctuator; the token's span still covers the digraph.
+pub const DIGRAPHS: &[(&str, Punct)] = &[
+    ("<:", Punct::LBracket),
+    (":>", Punct::RBracket),
+    ("<%", Punct::LBrace),
+    ("%>", Punct::RBrace),
+    ("%:", Punct::Hash),
+    ("%:%:", Punct::HashHash),
+];
@@ -0,0 +1,83 @@
+// Byte trie over the punctuator table, so the lexer finds the longest
+// punctuator at a position in one walk instead of testing every prefix
+#[derive(Debug, Clone)]
+pub struct Trie<T> {
+    nodes: Vec<Node<T>>,
+    // Length of the longest word
+    max_len: usize,
+}
+
+#[derive(Debug, Clone)]
+struct Node<T> {
+    children: Vec<(u8, usize)>,
+    // The value of the word spelled by the path to this node, if any
+    terminal: Option<T>,
+}
+
+impl<T> Node<T> {
+    fn new() -> Self {
+        Node { children: Vec::new(), terminal: None }
+    }
+}
+
+impl<T: Copy> Trie<T> {
+    pub fn new(words: impl IntoIterator<Item = (&'static str, T)>) -> Self {
+        let mut trie = Trie { nodes: vec![Node::new()], max_len: 0 };
+        for (word, value) in words {
+            trie.insert(word, value);
+        }
+        trie
+    }
+
+    fn insert(&mut self, word: &str, value: T) {
+        self.max_len = self.max_len.max(word.len());
+        let mut node = 0;
+        for &b in word.as_bytes() {
+            node = match self.child(node, b) {
+                Some(next) => next,
+                None => {
+                    self.nodes.push(Node::new());
+                    let next = self.nodes.len() - 1;
+                    self.nodes[node].children.push((b, next));
+                    next
+                }
+            };
+        }
+        self.nodes[node].terminal = Some(value);
+    }
+
+    fn child(&self, node: usize, b: u8) -> Option<usize> {
+        self.nodes[node]
+            .children
+            .iter()
+            .find(|(c, _)| *c == b)
+            .map(|(_, next)| *next)
+    }
+
+    /// Length of the longest word in bytes, the most `longest_match` reads.
+    pub fn max_len(&self) -> usize {
+        self.max_len
+    }
+
+    /// Whether some word starts with `b`.
+    pub fn starts_with(&self, b: u8) -> bool {
+        self.child(0, b).is_some()
+    }
+
+    /// The longest word that `input` starts with (maximal munch), with its
+    /// length in bytes.
+    pub fn longest_match(&self, input: impl IntoIterator<Item = u8>) -> Option<(T, usize)> {
+        let mut node = 0;
+        let mut longest = None;
+        for (i, b) in input.into_iter().enumerate() {
+            match self.child(node, b) {
+                Some(next) => node = next,
+                None => break,
+            }
+            if let Some(value) = self.nodes[node].terminal {
+                longest = Some((value, i + 1));
+            }
+        }
+        longest
+    }
+}
@@ -0,0 +1,208 @@
+use std::fmt;
+
+use crate::lexer::Lexer;
+use crate::token::{Position, PositionedToken, Span, Token};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TriviaKind {
+    /// A run of blanks within a line.
+    Whitespace,
+    /// `\n` or `\r\n`.
+    Newline,
+    /// Backslash-newlines joining physical lines into one logical line.
+    LineSplice,
+    Comment,
+}
+
+/// Source text between tokens that the grammar ignores.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Trivia<S = String> {
+    pub kind: TriviaKind,
+    pub text: S,
+    pub span: Span,
+}
+
+impl<S: AsRef<str>> Trivia<S> {
+    pub fn into_owned(self) -> Trivia {
+        Trivia {
+            kind: self.kind,
+            text: self.text.as_ref().to_string(),
+            span: self.span,
+        }
+    }
+}
+
+/// A token with the exact text it was lexed from and the trivia around it.
+/// Trailing trivia runs up to the end of the token's line, everything after
+/// it leads the next token. Writing the tokens of a file one after the other
+/// reproduces it byte for byte.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LosslessToken<S = String> {
+    pub leading: Vec<Trivia<S>>,
+    pub token: PositionedToken<S>,
+    // The source text of the token, unlike `Token::raw` with its quotes and
+    // comment markers
+    pub text: S,
+    pub trailing: Vec<Trivia<S>>,
+}
+
+impl<S: AsRef<str>> LosslessToken<S> {
+    pub fn into_owned(self) -> LosslessToken {
+        LosslessToken {
+            leading: self.leading.into_iter().map(Trivia::into_owned).collect(),
+            token: self.token.into_owned(),
+            text: self.text.as_ref().to_string(),
+            trailing: self.trailing.into_iter().map(Trivia::into_owned).collect(),
+        }
+    }
+}
+
+impl<S: AsRef<str>> fmt::Display for LosslessToken<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for trivia in &self.leading {
+            f.write_str(trivia.text.as_ref())?;
+        }
+        f.write_str(self.text.as_ref())?;
+        for trivia in &self.trailing {
+            f.write_str(trivia.text.as_ref())?;
+        }
+        Ok(())
+    }
+}
+
+/// Lossless mode of the lexer: comments and whitespace become trivia of
+/// the neighbouring tokens, and the last item is a `Token::Eof` whose
+/// leading trivia holds whatever follows the last token.
+pub struct Lossless<'src> {
+    input: &'src str,
+    lexer: Lexer<'src>,
+    // A token read while collecting trailing trivia
+    peeked: Option<PositionedToken<&'src str>>,
+    // Everything before it has been handed out
+    cursor: Position,
+    done: bool,
+}
+
+impl<'src> Lossless<'src> {
+    pub(crate) fn new(input: &'src str, lexer: Lexer<'src>, start: Position) -> Self {
+        Lossless {
+            input,
+            lexer,
+            peeked: None,
+            cursor: start,
+            done: false,
+        }
+    }
+
+    fn next_raw(&mut self) -> Option<PositionedToken<&'src str>> {
+        self.peeked.take().or_else(|| self.lexer.next())
+    }
+
+    // Split the whitespace from the cursor to `end` into trivia; with
+    // `line_only` stop at the first newline
+    fn whitespace(&mut self, end: usize, line_only: bool, out: &mut Vec<Trivia<&'src str>>) {
+        while self.cursor.offset < end {
+            let rest = &self.input[self.cursor.offset..end];
+            let start = self.cursor;
+            let splice = self.lexer.skip_splices_from(self.cursor.offset).min(end) - self.cursor.offset;
+            let (kind, len) = if splice > 0 {
+                (TriviaKind::LineSplice, splice)
+            } else if rest.starts_with('\n') {
+                (TriviaKind::Newline, 1)
+            } else if rest.starts_with("\r\n") {
+                (TriviaKind::Newline, 2)
+            } else {
+                // Up to a newline or splice
+                let mut len = rest
+                    .char_indices()
+                    .skip(1)
+                    .find(|(_, c)| matches!(c, '\n' | '\\' | '?'))
+                    .map_or(rest.len(), |(i, _)| i);
+                if rest[..len].ends_with('\r') && len < rest.len() {
+                    len -= 1;
+                }
+                (TriviaKind::Whitespace, len)
+            };
+            if kind == TriviaKind::Newline && line_only {
+                return;
+            }
+
+            let text = &rest[..len];
+            if kind == TriviaKind::Newline || kind == TriviaKind::LineSplice {
+                self.cursor.row += text.matches('\n').count();
+                self.cursor.column = 1;
+            } else {
+                self.cursor.column += text.chars().count();
+            }
+            self.cursor.offset += len;
+            out.push(Trivia { kind, text, span: Span::new(start, self.cursor) });
+        }
+    }
+
+    fn comment(&mut self, token: PositionedToken<&'src str>, out: &mut Vec<Trivia<&'src str>>) {
+        out.push(Trivia {
+            kind: TriviaKind::Comment,
+            text: &self.input[token.span.range()],
+            span: token.span,
+        });
+        self.cursor = token.span.end;
+    }
+}
+
+impl<'src> Iterator for Lossless<'src> {
+    type Item = LosslessToken<&'src str>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+
+        let mut leading = Vec::new();
+        let token = loop {
+            match self.next_raw() {
+                Some(token) => {
+                    self.whitespace(token.span.start.offset, false, &mut leading);
+                    if let Token::Comment(_) = token.token {
+                        self.comment(token, &mut leading);
+                    } else {
+                        break token;
+                    }
+                }
+                None => {
+                    self.whitespace(self.input.len(), false, &mut leading);
+                    self.done = true;
+                    return Some(LosslessToken {
+                        leading,
+                        token: PositionedToken { token: Token::Eof, span: Span::new(self.cursor, self.cursor) },
+                        text: "",
+                        trailing: Vec::new(),
+                    });
+                }
+            }
+        };
+
+        self.cursor = token.span.end;
+        let text = &self.input[token.span.range()];
+
+        let mut trailing = Vec::new();
+        loop {
+            match self.next_raw() {
+                Some(next) => {
+                    self.whitespace(next.span.start.offset, true, &mut trailing);
+                    if self.cursor.offset == next.span.start.offset && matches!(next.token, Token::Comment(_)) {
+                        self.comment(next, &mut trailing);
+                    } else {
+                        self.peeked = Some(next);
+                        break;
+                    }
+                }
+                None => {
+                    self.whitespace(self.input.len(), true, &mut trailing);
+                    break;
+                }
+            }
+        }
+
+        Some(LosslessToken { leading, token, text, trailing })
+    }
+}
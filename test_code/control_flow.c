@@ -0,0 +1,34 @@
+int count(const char *s, char c) {
+    int n = 0;
+    for (int i = 0; s[i] != 0; i++) {
+        if (s[i] != c)
+            continue;
+        n++;
+    }
+    return n;
+}
+
+int classify(int c) {
+    switch (c) {
+    case ' ':
+    case '\t':
+        return 0;
+    case '0':
+        c = 1;
+        /* fall through */
+    default:
+        c++;
+        break;
+    }
+    return c;
+}
+
+void wait(volatile int *flag) {
+    int tries;
+    for (tries = 0;; tries++) {
+        if (*flag)
+            goto done;
+    }
+done:
+    ;
+}
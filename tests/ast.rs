@@ -0,0 +1,97 @@
+use lexer_and_parser::ast::{BinOp, Block, Expr, Item, LogicalOp, Stmt, UnaryOp};
+use lexer_and_parser::{Lexer, Parser, Position};
+
+fn parse(source: &str) -> Block {
+    let (program, errors) = Parser::new(Lexer::new(source)).parse_program();
+    assert_eq!(errors, [], "parse errors");
+    let Some(Item::Block(block)) = program.items.into_iter().next() else { panic!("not a block") };
+    block
+}
+
+// The statements of `source` as one line each, expressions in prefix form
+fn shape(source: &str) -> Vec<String> {
+    parse(source).stmts.iter().map(stmt).collect()
+}
+
+fn stmt(stmt: &Stmt) -> String {
+    match stmt {
+        Stmt::Expr { expr: Some(e), .. } => expr(e),
+        Stmt::If { cond, then_branch, else_branch: Some(else_branch), .. } => {
+            format!("if {} then {} else {}", expr(cond), self::stmt(then_branch), self::stmt(else_branch))
+        }
+        Stmt::If { cond, then_branch, .. } => format!("if {} then {}", expr(cond), self::stmt(then_branch)),
+        Stmt::While { cond, body, .. } => format!("while {} {}", expr(cond), self::stmt(body)),
+        Stmt::DoWhile { body, cond, .. } => format!("do {} while {}", self::stmt(body), expr(cond)),
+        Stmt::Break { .. } => "break".to_string(),
+        Stmt::Block(block) => format!("{{{}}}", block.stmts.iter().map(self::stmt).collect::<Vec<_>>().join("; ")),
+        Stmt::Error { .. } => "error".to_string(),
+        stmt => panic!("not in these tests: {:?}", stmt),
+    }
+}
+
+fn expr(e: &Expr) -> String {
+    match e {
+        Expr::Binary { op, lhs, rhs, .. } => {
+            let op = match op {
+                BinOp::Add => "+",
+                BinOp::Sub => "-",
+                BinOp::Mul => "*",
+                BinOp::Div => "/",
+                BinOp::Lt => "<",
+                BinOp::Le => "<=",
+                BinOp::Gt => ">",
+                BinOp::Ge => ">=",
+                BinOp::Eq => "==",
+                BinOp::Ne => "!=",
+                op => panic!("not in these tests: {:?}", op),
+            };
+            format!("({} {} {})", op, expr(lhs), expr(rhs))
+        }
+        Expr::Logical { op, lhs, rhs, .. } => {
+            let op = match op {
+                LogicalOp::And => "&&",
+                LogicalOp::Or => "||",
+            };
+            format!("({} {} {})", op, expr(lhs), expr(rhs))
+        }
+        Expr::Assign { op: None, target, value, .. } => format!("{} = {}", expr(target), expr(value)),
+        Expr::Unary { op: UnaryOp::Not, operand, .. } => format!("(! {})", expr(operand)),
+        Expr::Index { base, index, .. } => format!("{}[{}]", expr(base), expr(index)),
+        Expr::Ident(ident) => ident.name.clone(),
+        Expr::Num(num) => num.literal.clone(),
+        Expr::Paren { expr: inner, .. } => format!("[{}]", expr(inner)),
+        Expr::Error { .. } => "error".to_string(),
+        e => panic!("not in these tests: {:?}", e),
+    }
+}
+
+#[test]
+fn assignments_follow_precedence_and_associativity() {
+    assert_eq!(
+        shape("{ x = a + 2 * (b - 1); y = a - b - c / d / 4; }"),
+        ["x = (+ a (* 2 [(- b 1)]))", "y = (- (- a b) (/ (/ c d) 4))"]
+    );
+}
+
+#[test]
+fn control_flow_statements() {
+    assert_eq!(
+        shape("{ if (x < 3) break else while (x) x = x - 1; do { y = 1; } while (y >= 2) if (a) { } }"),
+        ["if (< x 3) then break else while x x = (- x 1)", "do {y = 1} while (>= y 2)", "if a then {}"]
+    );
+}
+
+fn row_column(pos: Position) -> (usize, usize) {
+    (pos.row, pos.column)
+}
+
+#[test]
+fn nodes_carry_the_position_of_their_first_token() {
+    let block = parse("{\n  x = 1;\n  while (x) y = x;\n}");
+    assert_eq!(row_column(block.pos), (1, 1));
+    assert_eq!(row_column(block.stmts[0].pos()), (2, 3));
+    let Stmt::While { cond, body, .. } = &block.stmts[1] else { panic!("not a while: {:?}", block.stmts[1]) };
+    assert_eq!(row_column(block.stmts[1].pos()), (3, 3));
+    assert_eq!(row_column(cond.pos()), (3, 10));
+    assert_eq!(row_column(body.pos()), (3, 13));
+}
@@ -0,0 +1,79 @@
+use lexer_and_parser::cst::{self, SyntaxKind, SyntaxNode};
+use lexer_and_parser::{Lexer, Trivia, TriviaKind};
+
+fn parse(source: &str) -> SyntaxNode<&str> {
+    cst::parse(Lexer::new(source)).0
+}
+
+// Each token of `source` with the text of its leading and trailing trivia
+fn attached(source: &str) -> Vec<(Vec<&str>, &str, Vec<&str>)> {
+    Lexer::new(source).lossless().map(|t| (text(&t.leading), t.text, text(&t.trailing))).collect()
+}
+
+fn text<'a>(trivia: &[Trivia<&'a str>]) -> Vec<&'a str> {
+    trivia.iter().map(|t| t.text).collect()
+}
+
+#[test]
+fn the_tree_prints_back_its_source() {
+    let sources = [
+        "",
+        "{ x = 1; }",
+        "// head\n{ /* a */ x = 1; // b\n\n\n  if (x) break }\n// tail\n",
+        "{\r\n  x = 1;\r\n\r\n  y = x; // c\r\n}\r\n",
+        "{ x = 1 + \\\n 2; }",
+        "{ x = @; y = \"ab\n; 'c }",
+        "  \t\n",
+    ];
+    for source in sources {
+        let (tree, _) = cst::parse(Lexer::new(source));
+        assert_eq!(tree.to_string(), source);
+        assert_eq!(tree.into_owned().to_string(), source);
+    }
+}
+
+#[test]
+fn errors_keep_their_text_in_the_tree() {
+    let source = "{ x = @; y = ) ; }";
+    let (tree, errors) = cst::parse(Lexer::new(source));
+    assert_eq!(errors.len(), 2);
+    assert_eq!(tree.to_string(), source);
+    assert_eq!(tree.kind(), SyntaxKind::Program);
+}
+
+#[test]
+fn trailing_trivia_runs_to_the_end_of_the_line() {
+    assert_eq!(
+        attached("x = 1; // one\n\n/* two */ y"),
+        [
+            (vec![], "x", vec![" "]),
+            (vec![], "=", vec![" "]),
+            (vec![], "1", vec![]),
+            (vec![], ";", vec![" ", "// one"]),
+            (vec!["\n", "\n", "/* two */", " "], "y", vec![]),
+            (vec![], "", vec![]),
+        ]
+    );
+}
+
+#[test]
+fn trivia_after_the_last_token_belongs_to_eof() {
+    let tokens: Vec<_> = Lexer::new("x;\r\n// end\r\n").lossless().collect();
+    let eof = tokens.last().unwrap();
+    assert_eq!(eof.text, "");
+    let kinds: Vec<_> = eof.leading.iter().map(|t| t.kind).collect();
+    assert_eq!(kinds, [TriviaKind::Newline, TriviaKind::Comment, TriviaKind::Newline]);
+    assert_eq!(eof.leading[0].text, "\r\n");
+}
+
+#[test]
+fn nodes_own_the_trivia_of_their_tokens() {
+    let tree = parse("{ // a\n  /* b */ x = 1; // c\n}");
+    let block = tree.nodes().next().unwrap();
+    assert_eq!(block.kind(), SyntaxKind::Block);
+    let assign = block.nodes().next().unwrap();
+    assert_eq!(assign.kind(), SyntaxKind::ExprStmt);
+    // `// a` trails `{`, while the next line up to `x` leads it
+    assert_eq!(assign.to_string(), "\n  /* b */ x = 1; // c");
+    assert_eq!(block.tokens()[0].to_string(), "{ // a");
+}
@@ -0,0 +1,204 @@
+use lexer_and_parser::ast::{Declaration, Declarator, DeclSpecifiers, Derived, Expr, Initializer, Item, Stmt, TypeSpecifier};
+use lexer_and_parser::{Lexer, Parser};
+
+fn parse(source: &str) -> Vec<Stmt> {
+    let (program, errors) = Parser::new(Lexer::new(source)).parse_program();
+    assert!(errors.is_empty(), "{:?}", errors);
+    let Some(Item::Block(block)) = program.items.into_iter().next() else { panic!("not a block") };
+    block.stmts
+}
+
+fn declarations(source: &str) -> Vec<Declaration> {
+    parse(&format!("{{ {} }}", source))
+        .into_iter()
+        .map(|stmt| match stmt {
+            Stmt::Decl(decl) => decl,
+            stmt => panic!("not a declaration: {:?}", stmt),
+        })
+        .collect()
+}
+
+// What each declarator declares, in words
+fn describe(source: &str) -> Vec<String> {
+    let mut described = Vec::new();
+    for decl in declarations(source) {
+        for init_declarator in &decl.declarators {
+            let declarator = &init_declarator.declarator;
+            let name = declarator.name.as_ref().map_or("_", |name| &name.name);
+            described.push(format!("{}: {}", name, type_of(&decl.specifiers, declarator)));
+        }
+    }
+    described
+}
+
+fn type_of(specifiers: &DeclSpecifiers, declarator: &Declarator) -> String {
+    let mut words = Vec::new();
+    for derived in &declarator.derived {
+        words.push(match derived {
+            Derived::Pointer { qualifiers } if qualifiers.is_empty() => "pointer to".to_string(),
+            Derived::Pointer { qualifiers } => format!("{:?} pointer to", qualifiers),
+            Derived::Array { len: Some(len) } => match len.as_ref() {
+                Expr::Num(num) => format!("array[{}] of", num.literal),
+                _ => "array[expr] of".to_string(),
+            },
+            Derived::Array { len: None } => "array[] of".to_string(),
+            Derived::Function { params, variadic } => {
+                let mut params: Vec<_> = params
+                    .iter()
+                    .map(|param| match &param.declarator.name {
+                        Some(name) => format!("{} {}", type_of(&param.specifiers, &param.declarator), name.name),
+                        None => type_of(&param.specifiers, &param.declarator),
+                    })
+                    .collect();
+                if *variadic {
+                    params.push("...".to_string());
+                }
+                format!("function({}) returning", params.join(", "))
+            }
+        });
+    }
+    words.push(match &specifiers.ty {
+        TypeSpecifier::Builtin(keywords) => {
+            keywords.iter().chain(&specifiers.qualifiers).map(|k| k.as_str()).collect::<Vec<_>>().join(" ")
+        }
+        TypeSpecifier::Struct(spec) => {
+            let keyword = if spec.union { "union" } else { "struct" };
+            format!("{} {}", keyword, spec.tag.as_ref().map_or("<anonymous>", |tag| &tag.name))
+        }
+        TypeSpecifier::Enum(spec) => format!("enum {}", spec.tag.as_ref().map_or("<anonymous>", |tag| &tag.name)),
+        TypeSpecifier::Typedef(name) => name.name.clone(),
+    });
+    words.join(" ")
+}
+
+#[test]
+fn declarators_read_from_the_name_outward() {
+    assert_eq!(
+        describe("int (*fp[3])(char); char *argv[], **envp; int *const *p;"),
+        [
+            "fp: array[3] of pointer to function(char) returning int",
+            "argv: array[] of pointer to char",
+            "envp: pointer to pointer to char",
+            "p: pointer to [Const] pointer to int",
+        ]
+    );
+    assert_eq!(
+        describe("void (*signal(int sig, void (*)(int)))(int); int (*(*x)[5])(void); long f(const char *, ...);"),
+        [
+            "signal: function(int sig, pointer to function(int) returning void) returning pointer to function(int) returning void",
+            "x: pointer to array[5] of pointer to function(void) returning int",
+            "f: function(pointer to char const, ...) returning long",
+        ]
+    );
+}
+
+#[test]
+fn specifiers_in_any_order() {
+    let decls = declarations("static const unsigned long int x = 1; inline extern int f(register int a);");
+    assert_eq!(decls[0].specifiers.storage.iter().map(|k| k.as_str()).collect::<Vec<_>>(), ["static"]);
+    assert_eq!(decls[0].specifiers.qualifiers.iter().map(|k| k.as_str()).collect::<Vec<_>>(), ["const"]);
+    assert_eq!(describe("unsigned long int x;"), ["x: unsigned long int"]);
+    assert_eq!(decls[1].specifiers.function.iter().map(|k| k.as_str()).collect::<Vec<_>>(), ["inline"]);
+}
+
+#[test]
+fn struct_and_enum_definitions() {
+    let source = "
+        typedef struct { int a; char b, *c; } my_struct;
+        enum my_enum { eA, eB, eC = 5, eD, };
+        union u { struct point { int x, y; } p; float f[2]; } v;
+        struct point q;
+    ";
+    let decls = declarations(source);
+    let TypeSpecifier::Struct(spec) = &decls[0].specifiers.ty else { panic!("not a struct") };
+    assert!(decls[0].specifiers.is_typedef() && spec.tag.is_none());
+    let fields: Vec<_> = spec.fields.as_ref().unwrap().iter().flat_map(|f| &f.declarators).collect();
+    assert_eq!(fields.iter().map(|f| f.declarator.name.as_ref().unwrap().name.as_str()).collect::<Vec<_>>(), ["a", "b", "c"]);
+
+    let TypeSpecifier::Enum(spec) = &decls[1].specifiers.ty else { panic!("not an enum") };
+    let enumerators = spec.enumerators.as_ref().unwrap();
+    assert_eq!(enumerators.iter().map(|e| e.name.name.as_str()).collect::<Vec<_>>(), ["eA", "eB", "eC", "eD"]);
+    assert!(enumerators[2].value.is_some() && decls[1].declarators.is_empty());
+
+    assert_eq!(describe(source)[1..], ["v: union u".to_string(), "q: struct point".to_string()]);
+}
+
+#[test]
+fn bit_fields() {
+    let (_, errors) = Parser::new(Lexer::new("struct S { int x : 3; unsigned : 0; } s;")).parse_program();
+    assert_eq!(errors, []);
+
+    let decls = declarations("struct { int a : 3, b; unsigned : 0; long c : N ? 1 : 2, *d; } s;");
+    let TypeSpecifier::Struct(spec) = &decls[0].specifiers.ty else { panic!("not a struct") };
+    let fields: Vec<_> = spec
+        .fields
+        .as_ref()
+        .unwrap()
+        .iter()
+        .flat_map(|f| &f.declarators)
+        .map(|f| {
+            let name = f.declarator.name.as_ref().map_or("_", |name| &name.name);
+            let width = match &f.width {
+                Some(Expr::Num(num)) => num.literal.as_str(),
+                Some(Expr::Conditional { .. }) => "conditional",
+                Some(width) => panic!("unexpected width: {:?}", width),
+                None => "-",
+            };
+            format!("{}: {}", name, width)
+        })
+        .collect();
+    assert_eq!(fields, ["a: 3", "b: -", "_: 0", "c: conditional", "d: -"]);
+}
+
+#[test]
+fn initializer_lists() {
+    let decls = declarations("int a[] = { 1, { 2, 3, }, {} }, b = 4; char *s = \"ab\" \"c\", c = 'c';");
+    let Some(Initializer::List { items, .. }) = &decls[0].declarators[0].init else { panic!("not a list") };
+    assert_eq!(items.len(), 3);
+    assert!(matches!(&items[1], Initializer::List { items, .. } if items.len() == 2));
+    assert!(matches!(&decls[0].declarators[1].init, Some(Initializer::Expr(Expr::Num(_)))));
+    assert!(matches!(&decls[1].declarators[0].init, Some(Initializer::Expr(Expr::Str { value, .. })) if value == b"abc"));
+    assert!(matches!(&decls[1].declarators[1].init, Some(Initializer::Expr(Expr::Char { value: 99, .. }))));
+}
+
+#[test]
+fn typedef_names_are_scoped() {
+    let source = "{
+        typedef unsigned long T;
+        T *x;
+        { int T; T = 2; }
+        T y;
+        x = (T *)sizeof(T);
+    }";
+    let stmts = parse(source);
+    assert!(matches!(&stmts[1], Stmt::Decl(decl) if matches!(&decl.specifiers.ty, TypeSpecifier::Typedef(name) if name.name == "T")));
+    assert!(matches!(&stmts[4], Stmt::Expr { expr: Some(Expr::Assign { value, .. }), .. } if matches!(value.as_ref(), Expr::Cast { .. })));
+}
+
+#[test]
+fn the_declarations_of_the_tokenizer_example() {
+    let source = std::fs::read_to_string("test_code/tokenize.c").unwrap();
+    let (program, errors) = Parser::new(Lexer::new(&source)).parse_program();
+    assert!(errors.is_empty(), "{:?}", errors);
+    let [Item::Decl(my_struct), Item::Decl(my_enum), Item::Function(main)] = program.items.as_slice() else {
+        panic!("not two declarations and a function: {:?}", program.items);
+    };
+    assert!(my_struct.specifiers.is_typedef() && matches!(my_enum.specifiers.ty, TypeSpecifier::Enum(_)));
+    let locals = main.body.stmts.iter().filter(|stmt| matches!(stmt, Stmt::Decl(_))).count();
+    assert_eq!(locals, 5);
+}
+
+#[test]
+fn errors_in_declarations() {
+    let errors = |source: &str| -> Vec<String> {
+        let (_, errors) = Parser::new(Lexer::new(source)).parse_program();
+        errors.iter().map(|e| e.to_string()).collect()
+    };
+    assert_eq!(errors("{ int 3; }"), ["expected one of type, `;`, `*`, identifier or `(`, found number `3`"]);
+    assert_eq!(errors("{ static x; }"), ["expected type, found identifier `x`"]);
+    assert_eq!(errors("{ int a[2; }"), ["expected `]`, found `;`"]);
+    // Only fields have a width
+    assert_eq!(errors("{ int a : 1; }"), ["expected one of `[`, `(`, `=`, `,` or `;`, found `:`"]);
+    assert_eq!(errors("struct { int a : ; } s;")[0], "expected expression, found `;`");
+    assert_eq!(errors("struct { int; } s;")[0], "expected one of type, `:`, `*`, identifier or `(`, found `;`");
+}
@@ -0,0 +1,149 @@
+use lexer_and_parser::diagnostics::{Diagnostic, Renderer};
+use lexer_and_parser::{Lexer, Parser, Position, Span};
+
+fn span(start: (usize, usize, usize), end: (usize, usize, usize)) -> Span {
+    let position = |(offset, row, column)| Position { offset, row, column };
+    Span::new(position(start), position(end))
+}
+
+fn render(source: &str, diagnostic: &Diagnostic) -> String {
+    Renderer::new("main.c", source).render(diagnostic)
+}
+
+// The first parse error of `source`, rendered
+fn render_parse_error(source: &str) -> String {
+    let (_, errors) = Parser::new(Lexer::new(source)).parse_program();
+    render(source, &Diagnostic::from_parse_error(&errors[0]))
+}
+
+#[test]
+fn a_caret_under_a_single_token() {
+    assert_eq!(
+        render_parse_error("{\n    i = 2 3;\n}"),
+        "\
+error: expected `;`, found number `3`
+ --> main.c:2:11
+  |
+2 |     i = 2 3;
+  |           ^ expected `;`
+"
+    );
+}
+
+#[test]
+fn an_underline_over_several_columns() {
+    let source = "{ x = y; }";
+    let diagnostic = Diagnostic::warning("unused value")
+        .with_label(span((2, 1, 3), (7, 1, 8)), "this assignment")
+        .with_label(span((6, 1, 7), (7, 1, 8)), "reads `y`")
+        .with_note("assignments are expressions");
+    assert_eq!(
+        render(source, &diagnostic),
+        "\
+warning: unused value
+ --> main.c:1:3
+  |
+1 | { x = y; }
+  |   ^^^^^ this assignment
+1 | { x = y; }
+  |       - reads `y`
+  |
+  = note: assignments are expressions
+"
+    );
+}
+
+#[test]
+fn a_span_over_several_lines() {
+    let source = "{\n    x = (1 +\n         2);\n}";
+    let diagnostic = Diagnostic::error("too long").with_label(span((10, 2, 9), (21, 3, 12)), "this expression");
+    assert_eq!(
+        render(source, &diagnostic),
+        "\
+error: too long
+ --> main.c:2:9
+  |
+2 |     x = (1 +
+  |         ^^^^
+3 |          2);
+  | ^^^^^^^^^^^ this expression
+"
+    );
+}
+
+#[test]
+fn tabs_are_kept_in_the_padding() {
+    let source = "{\n\tx = @ 1;\n}";
+    let lexed = Lexer::new(source).tokenize().iter().find_map(Diagnostic::from_lex_error).unwrap();
+    assert_eq!(
+        render(source, &lexed),
+        "\
+error: Unexpected character: @
+ --> main.c:2:6
+  |
+2 | \tx = @ 1;
+  | \t    ^ invalid token
+"
+    );
+}
+
+#[test]
+fn spans_at_the_end_of_the_input() {
+    assert_eq!(
+        render_parse_error("{ x = 1"),
+        "\
+error: unexpected end of input, expected `;`
+ --> main.c:1:8
+  |
+1 | { x = 1
+  |        ^ expected `;`
+"
+    );
+    // Past the last newline there is an empty line to point at
+    let diagnostic = Diagnostic::error("expected `}`").with_label(span((6, 2, 1), (6, 2, 1)), "here");
+    assert_eq!(
+        render("{ x;\n", &diagnostic),
+        "\
+error: expected `}`
+ --> main.c:2:1
+  |
+2 | 
+  | ^ here
+"
+    );
+}
+
+#[test]
+fn parse_errors_explain_what_was_expected() {
+    assert_eq!(
+        render_parse_error("{ ) }"),
+        "\
+error: expected statement, found `)`
+ --> main.c:1:3
+  |
+1 | { ) }
+  |   ^ expected statement
+  |
+  = note: a statement starts with one of `}`, identifier, type, `;`, `if`, `while`, `do`, `for`, `switch`, \
+`case`, `default`, `goto`, `continue`, `break`, `return`, `{`, `(`, number, character literal, string literal, \
+`sizeof`, operator
+"
+    );
+    let (_, errors) = Parser::new(Lexer::new("{ x = 1 }")).parse_program();
+    let diagnostic = Diagnostic::from_parse_error(&errors[0]);
+    assert_eq!(diagnostic.message, "expected `;`, found `}`");
+    assert_eq!(diagnostic.labels[0].span, errors[0].span);
+
+    // With a single token expected, the label names it
+    let (_, errors) = Parser::new(Lexer::new("{ while x) break }")).parse_program();
+    assert_eq!(Diagnostic::from_parse_error(&errors[0]).labels[0].message, "expected `(`");
+}
+
+#[test]
+fn only_error_tokens_make_lex_diagnostics() {
+    let tokens = Lexer::new("x @").tokenize();
+    assert!(Diagnostic::from_lex_error(&tokens[0]).is_none());
+    let diagnostic = Diagnostic::from_lex_error(&tokens[1]).unwrap();
+    assert_eq!(diagnostic.message, "Unexpected character: @");
+    assert_eq!(diagnostic.labels[0].span, tokens[1].span);
+}
@@ -0,0 +1,83 @@
+use lexer_and_parser::{Expected, Keyword, Lexer, ParseError, ParseErrorKind, Parser, Position, Punct, Span, Token};
+
+fn first_error(source: &str) -> ParseError {
+    let (_, errors) = Parser::new(Lexer::new(source)).parse_program();
+    errors.into_iter().next().expect("an error")
+}
+
+#[test]
+fn a_single_expected_token() {
+    let err = first_error("{ while x) break }");
+    assert_eq!(err.kind, ParseErrorKind::UnexpectedToken);
+    assert_eq!(err.expected, [Expected::Symbol(Punct::LParen)]);
+    assert_eq!(err.to_string(), "expected `(`, found identifier `x`");
+}
+
+#[test]
+fn several_expected_tokens() {
+    let err = first_error("{ x = a b; }");
+    assert_eq!(err.kind, ParseErrorKind::UnexpectedToken);
+    assert_eq!(err.to_string(), "expected `;`, found identifier `b`");
+
+    let err = first_error("{ int 3; }");
+    assert_eq!(err.to_string(), "expected one of type, `;`, `*`, identifier or `(`, found number `3`");
+}
+
+#[test]
+fn nothing_to_start_a_construct() {
+    let err = first_error("int x; 42");
+    assert_eq!(err.kind, ParseErrorKind::ExpectedDeclaration);
+    assert_eq!(err.to_string(), "expected declaration, found number `42`");
+
+    let err = first_error("{ ) }");
+    assert_eq!(err.kind, ParseErrorKind::ExpectedStatement);
+    assert_eq!(err.to_string(), "expected statement, found `)`");
+    assert!(err.expected.contains(&Expected::Keyword(Keyword::If)));
+
+    let err = first_error("{ x = ]; }");
+    assert_eq!(err.kind, ParseErrorKind::ExpectedExpression);
+    assert_eq!(err.to_string(), "expected expression, found `]`");
+}
+
+#[test]
+fn the_input_ends_early() {
+    let err = first_error("{ x = 1");
+    assert_eq!(err.kind, ParseErrorKind::UnexpectedEof);
+    assert_eq!(*err.found, Token::Eof);
+    assert_eq!(err.to_string(), "unexpected end of input, expected `;`");
+
+    let err = first_error("{ x = 1;");
+    assert_eq!(err.to_string(), "unexpected end of input, expected `}`");
+}
+
+#[test]
+fn the_lexer_rejected_the_token() {
+    let err = first_error("{ x = @; }");
+    assert_eq!(err.kind, ParseErrorKind::InvalidToken);
+    assert_eq!(err.to_string(), "invalid token: Unexpected character: @");
+}
+
+#[test]
+fn expected_sets_of_any_size() {
+    let start = Position { offset: 0, row: 1, column: 1 };
+    let err = |expected: Vec<Expected>| ParseError {
+        kind: ParseErrorKind::UnexpectedToken,
+        span: Span::new(start, start),
+        expected,
+        found: Box::new(Token::Symbol(Punct::Comma)),
+    };
+    assert_eq!(err(vec![]).to_string(), "expected nothing, found `,`");
+    assert_eq!(err(vec![Expected::Identifier]).to_string(), "expected identifier, found `,`");
+    assert_eq!(
+        err(vec![Expected::Number, Expected::Symbol(Punct::RParen)]).to_string(),
+        "expected one of number or `)`, found `,`"
+    );
+    assert_eq!(
+        err(vec![Expected::Identifier, Expected::Number, Expected::Keyword(Keyword::Else)]).to_string(),
+        "expected one of identifier, number or `else`, found `,`"
+    );
+    assert_eq!(
+        err(vec![Expected::CharLiteral, Expected::StringLiteral, Expected::EndOfInput]).to_string(),
+        "expected one of character literal, string literal or end of input, found `,`"
+    );
+}
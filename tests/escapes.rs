@@ -0,0 +1,75 @@
+use lexer_and_parser::{Lexer, Token};
+
+// The one token in `source`
+fn token(source: &str) -> Token<&str> {
+    let tokens: Vec<_> = Lexer::new(source).map(|t| t.token).collect();
+    match tokens.as_slice() {
+        [token] => token.clone(),
+        tokens => panic!("not one token: {:?}", tokens),
+    }
+}
+
+fn char_literal(source: &str) -> (&str, u32) {
+    match token(source) {
+        Token::CharLiteral { raw, value } => (raw, value),
+        token => panic!("not a character constant: {:?}", token),
+    }
+}
+
+fn string_literal(source: &str) -> (&str, Vec<u8>) {
+    match token(source) {
+        Token::StringLiteral { raw, value } => (raw, value),
+        token => panic!("not a string literal: {:?}", token),
+    }
+}
+
+fn error(source: &str) -> String {
+    match token(source) {
+        Token::Error(message) => message,
+        token => panic!("not an error: {:?}", token),
+    }
+}
+
+#[test]
+fn simple_escapes() {
+    assert_eq!(char_literal(r"'\n'"), (r"\n", 10));
+    assert_eq!(char_literal(r"'\t'"), (r"\t", 9));
+    assert_eq!(char_literal(r"'\\'"), (r"\\", 92));
+    assert_eq!(char_literal(r"'\''"), (r"\'", 39));
+    assert_eq!(char_literal(r"'\?'"), (r"\?", 63));
+    assert_eq!(string_literal(r#""a\"b\n""#), (r#"a\"b\n"#, b"a\"b\n".to_vec()));
+}
+
+#[test]
+fn octal_escapes() {
+    assert_eq!(char_literal(r"'\101'"), (r"\101", 65));
+    assert_eq!(char_literal(r"'\0'"), (r"\0", 0));
+    // At most three digits belong to the escape
+    assert_eq!(string_literal(r#""\1011""#), (r"\1011", b"A1".to_vec()));
+    assert_eq!(error(r"'\777'"), "Octal escape sequence out of range");
+}
+
+#[test]
+fn hexadecimal_escapes() {
+    assert_eq!(char_literal(r"'\x41'"), (r"\x41", 65));
+    assert_eq!(string_literal(r#""\x41\x7a""#), (r"\x41\x7a", b"Az".to_vec()));
+    // Every following hex digit belongs to the escape
+    assert_eq!(error(r"'\x1234'"), "Hex escape sequence out of range");
+    assert_eq!(error(r"'\xg'"), r"\x used with no following hex digits");
+}
+
+#[test]
+fn universal_character_names() {
+    assert_eq!(char_literal(r"'\u00E9'"), (r"\u00E9", 0xE9));
+    assert_eq!(char_literal(r"'\U0001F600'"), (r"\U0001F600", 0x1F600));
+    assert_eq!(string_literal(r#""\u00E9""#), (r"\u00E9", "é".as_bytes().to_vec()));
+    assert_eq!(string_literal(r#""\U0001F600""#), (r"\U0001F600", "😀".as_bytes().to_vec()));
+    assert_eq!(error(r"'\u00E'"), r"Incomplete universal character name \u");
+    assert_eq!(error(r"'\u0041'"), r"Invalid universal character \u0041");
+}
+
+#[test]
+fn unknown_escapes_are_errors() {
+    assert_eq!(error(r#""\q""#), r"Unknown escape sequence '\q'");
+    assert_eq!(error(r"'\q'"), r"Unknown escape sequence '\q'");
+}
@@ -0,0 +1,129 @@
+use lexer_and_parser::ast::{Expr, Item, LogicalOp, Stmt, TypeName, TypeSpecifier};
+use lexer_and_parser::{cst, Assoc, Expected, LanguageProfile, Lexer, LexerConfig, OperatorTable, Parser, Precedence};
+
+fn parse_condition(source: &str, config: &LexerConfig, operators: OperatorTable) -> Expr {
+    let source = format!("{{ while ({}) break }}", source);
+    let mut parser = Parser::new(Lexer::new_with_config(&source, config)).with_operators(operators);
+    let (program, errors) = parser.parse_program();
+    assert!(errors.is_empty(), "{:?}", errors);
+    let Some(Item::Block(block)) = program.items.into_iter().next() else { panic!("not a block") };
+    match block.stmts.into_iter().next() {
+        Some(Stmt::While { cond, .. }) => cond,
+        stmt => panic!("not a while: {:?}", stmt),
+    }
+}
+
+fn condition(source: &str) -> Expr {
+    parse_condition(source, &LexerConfig::default(), OperatorTable::default())
+}
+
+// The expression with every operator and its operands in parentheses
+fn show(expr: &Expr) -> String {
+    match expr {
+        Expr::Binary { op, lhs, rhs, .. } => format!("({} {:?} {})", show(lhs), op, show(rhs)),
+        Expr::Logical { op, lhs, rhs, .. } => format!("({} {:?} {})", show(lhs), op, show(rhs)),
+        Expr::Unary { op, operand, .. } => format!("({:?} {})", op, show(operand)),
+        Expr::Assign { op, target, value, .. } => format!("({} {:?}= {})", show(target), op, show(value)),
+        Expr::Conditional { cond, then_branch, else_branch, .. } => {
+            format!("({} ? {} : {})", show(cond), show(then_branch), show(else_branch))
+        }
+        Expr::Comma { lhs, rhs, .. } => format!("({}, {})", show(lhs), show(rhs)),
+        Expr::Cast { ty, expr, .. } => format!("(({}) {})", show_type(ty), show(expr)),
+        Expr::SizeofExpr { expr, .. } => format!("(sizeof {})", show(expr)),
+        Expr::SizeofType { ty, .. } => format!("(sizeof {})", show_type(ty)),
+        Expr::Index { base, index, .. } => format!("{}[{}]", show(base), show(index)),
+        Expr::Call { callee, args, .. } => {
+            format!("{}({})", show(callee), args.iter().map(show).collect::<Vec<_>>().join(", "))
+        }
+        Expr::Member { base, member, arrow, .. } => format!("{}{}{}", show(base), if *arrow { "->" } else { "." }, member.name),
+        Expr::Ident(ident) => ident.name.clone(),
+        Expr::Num(num) => num.literal.clone(),
+        Expr::Char { value, .. } => format!("{:?}", char::from_u32(*value).unwrap()),
+        Expr::Str { value, .. } => format!("{:?}", String::from_utf8_lossy(value)),
+        Expr::Paren { expr, .. } => show(expr),
+        Expr::Error { .. } => "<error>".to_string(),
+    }
+}
+
+// Builtin types and pointers to them are enough here
+fn show_type(ty: &TypeName) -> String {
+    let TypeSpecifier::Builtin(keywords) = &ty.specifiers.ty else { panic!("not a builtin type: {:?}", ty) };
+    let keywords: Vec<_> = keywords.iter().map(|k| k.as_str()).collect();
+    format!("{}{}", keywords.join(" "), "*".repeat(ty.declarator.derived.len()))
+}
+
+#[test]
+fn binary_operators_follow_the_c_precedence_levels() {
+    assert_eq!(show(&condition("i < n && a[i] != 0")), "((i Lt n) And (a[i] Ne 0))");
+    assert_eq!(
+        show(&condition("a || b && c | d ^ e & f == g < h << i + j * k")),
+        "(a Or (b And (c BitOr (d BitXor (e BitAnd (f Eq (g Lt (h Shl (i Add (j Mul k))))))))))"
+    );
+    assert_eq!(
+        show(&condition("a * b + c << d < e == f & g ^ h | i && j || k")),
+        "((((((((((a Mul b) Add c) Shl d) Lt e) Eq f) BitAnd g) BitXor h) BitOr i) And j) Or k)"
+    );
+    assert_eq!(show(&condition("a - b - c % d / e")), "((a Sub b) Sub ((c Rem d) Div e))");
+}
+
+#[test]
+fn assignment_and_conditional_group_to_the_right() {
+    assert_eq!(show(&condition("a = b += c")), "(a None= (b Some(Add)= c))");
+    assert_eq!(show(&condition("a ? b : c ? d : e")), "(a ? b : (c ? d : e))");
+    assert_eq!(show(&condition("x = a || b ? c, d : e")), "(x None= ((a Or b) ? (c, d) : e))");
+    assert_eq!(show(&condition("a = 1, b = 2, c")), "(((a None= 1), (b None= 2)), c)");
+}
+
+#[test]
+fn prefix_postfix_casts_and_sizeof() {
+    assert_eq!(show(&condition("-a++ * !~b")), "((Neg (PostInc a)) Mul (Not (BitNot b)))");
+    assert_eq!(show(&condition("*p->next.value-- + &x[1][2]")), "((Deref (PostDec p->next.value)) Add (AddrOf x[1][2]))");
+    assert_eq!(show(&condition("++i + --j")), "((PreInc i) Add (PreDec j))");
+    assert_eq!(show(&condition("(unsigned long *)p + 1")), "(((unsigned long*) p) Add 1)");
+    assert_eq!(show(&condition("sizeof x * sizeof(int) + sizeof (y)")), "(((sizeof x) Mul (sizeof int)) Add (sizeof y))");
+    assert_eq!(show(&condition("(char)-c")), "((char) (Neg c))");
+}
+
+#[test]
+fn short_circuit_nodes_point_at_their_operator() {
+    let cond = condition("a && b");
+    let Expr::Logical { op: LogicalOp::And, pos, .. } = cond else {
+        panic!("not a logical and: {:?}", cond);
+    };
+    assert_eq!((pos.row, pos.column), (1, 12));
+}
+
+#[test]
+fn profiles_add_operators_to_the_table() {
+    let config = LexerConfig::new(LanguageProfile::C17).with_punct("**").with_punct("@");
+    let operators = OperatorTable::new(LanguageProfile::C17)
+        .with_binary("**", Precedence::Unary, Assoc::Right)
+        .with_binary("&", Precedence::Additive, Assoc::Left)
+        .with_prefix("@");
+    let cond = parse_condition("-a ** b ** @c & d * e", &config, operators);
+    assert_eq!(show(&cond), "((Neg (a Extension(\"**\") (b Extension(\"**\") (Extension(\"@\") c)))) BitAnd (d Mul e))");
+
+    // The teaching profile has no `%` to parse
+    let teaching = LexerConfig::new(LanguageProfile::Teaching);
+    assert!(OperatorTable::new(LanguageProfile::Teaching).infix(lexer_and_parser::Punct::Percent).is_none());
+    assert_eq!(show(&parse_condition("a + b", &teaching, OperatorTable::new(LanguageProfile::Teaching))), "(a Add b)");
+}
+
+// Operators are left out of what was expected after an operand, only the
+// token closing the expression is named
+#[test]
+fn errors_expect_the_end_of_the_expression() {
+    let (_, errors) = Parser::new(Lexer::new("{ while (a b) break }")).parse_program();
+    assert_eq!(errors[0].expected, [Expected::Symbol(lexer_and_parser::Punct::RParen)]);
+    assert_eq!(errors[0].to_string(), "expected `)`, found identifier `b`");
+    let (_, errors) = Parser::new(Lexer::new("{ while (a ? b) break }")).parse_program();
+    assert_eq!(errors[0].to_string(), "expected `:`, found `)`");
+}
+
+#[test]
+fn syntax_tree_keeps_the_source() {
+    let source = "{ while (i < n && a[i] != 0 || !(done)) i = (int)sizeof(char *) ? p->x++ : -~i; }";
+    let (tree, errors) = cst::parse(Lexer::new(source));
+    assert!(errors.is_empty(), "{:?}", errors);
+    assert_eq!(tree.to_string(), source);
+}
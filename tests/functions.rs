@@ -0,0 +1,119 @@
+use lexer_and_parser::ast::{Derived, Expr, Item, Stmt};
+use lexer_and_parser::{cst, Document, Lexer, Parser, TextEdit};
+
+fn parse(source: &str) -> Vec<Item> {
+    let (program, errors) = Parser::new(Lexer::new(source)).parse_program();
+    assert!(errors.is_empty(), "{:?}", errors);
+    program.items
+}
+
+fn errors(source: &str) -> Vec<String> {
+    let (_, errors) = Parser::new(Lexer::new(source)).parse_program();
+    errors.iter().map(|e| e.to_string()).collect()
+}
+
+// The statements of the function definition `source`
+fn body(source: &str) -> Vec<Stmt> {
+    match parse(source).into_iter().next() {
+        Some(Item::Function(function)) => function.body.stmts,
+        item => panic!("not a function: {:?}", item),
+    }
+}
+
+#[test]
+fn a_translation_unit_of_functions_and_globals() {
+    let items = parse(
+        "
+        static int count = 0, *last;
+        int add(int a, int b) { return a + b; }
+        extern void reset(void);
+        void reset(void) { count = 0; return; }
+        int main(int argc, char *argv[]) { return add(argc, 1); }
+    ",
+    );
+    let kinds: Vec<_> = items
+        .iter()
+        .map(|item| match item {
+            Item::Function(function) => format!("fn {}", function.declarator.name.as_ref().unwrap().name),
+            Item::Decl(decl) => format!("{} declarators", decl.declarators.len()),
+            item => panic!("unexpected item: {:?}", item),
+        })
+        .collect();
+    assert_eq!(kinds, ["2 declarators", "fn add", "1 declarators", "fn reset", "fn main"]);
+
+    let Item::Function(main) = &items[4] else { unreachable!() };
+    let Some(Derived::Function { params, variadic: false }) = main.declarator.derived.first() else {
+        panic!("not a function declarator");
+    };
+    assert_eq!(params.len(), 2);
+}
+
+#[test]
+fn return_statements_and_calls() {
+    let stmts = body("int f(void) { return; return g(a, b = 1, (c, d))(x)[0]; return h(); }");
+    assert!(matches!(&stmts[0], Stmt::Return { value: None, .. }));
+    let Stmt::Return { value: Some(Expr::Index { base, .. }), .. } = &stmts[1] else { panic!("not an index") };
+    let Expr::Call { callee, args, .. } = base.as_ref() else { panic!("not a call") };
+    assert!(matches!(callee.as_ref(), Expr::Call { args, .. } if args.len() == 3));
+    assert!(matches!(args.as_slice(), [Expr::Ident(x)] if x.name == "x"));
+    assert!(matches!(&stmts[2], Stmt::Return { value: Some(Expr::Call { args, .. }), .. } if args.is_empty()));
+}
+
+#[test]
+fn assignments_to_any_lvalue() {
+    let stmts = body("void f(void) { x = 1; *p = 2; a[i] = 3; s.x = 4; ((int *)&v[0] + 1)[2] = 5; }");
+    let targets: Vec<_> = stmts
+        .iter()
+        .map(|stmt| match stmt {
+            Stmt::Expr { expr: Some(Expr::Assign { target, .. }), .. } => match target.as_ref() {
+                Expr::Ident(_) => "name",
+                Expr::Unary { .. } => "deref",
+                Expr::Index { .. } => "index",
+                Expr::Member { .. } => "member",
+                target => panic!("unexpected target: {:?}", target),
+            },
+            stmt => panic!("unexpected statement: {:?}", stmt),
+        })
+        .collect();
+    assert_eq!(targets, ["name", "deref", "index", "member", "index"]);
+}
+
+// `T * x;` is a multiplication once a parameter hides the typedef name
+#[test]
+fn parameters_are_in_scope_in_the_body() {
+    let items = parse("typedef int T; int f(int T) { T = 2; return T * x; } T y;");
+    let Item::Function(f) = &items[1] else { panic!("not a function") };
+    assert!(matches!(&f.body.stmts[0], Stmt::Expr { expr: Some(Expr::Assign { .. }), .. }));
+    assert!(matches!(&f.body.stmts[1], Stmt::Return { value: Some(Expr::Binary { .. }), .. }));
+    assert!(matches!(&items[2], Item::Decl(_)));
+}
+
+#[test]
+fn errors_at_file_scope() {
+    assert_eq!(errors("x = 1;"), ["expected declaration, found identifier `x`"]);
+    assert_eq!(errors("int f() { return 1 }"), ["expected `;`, found `}`"]);
+    // Recovery skips the broken item, braces and all
+    assert_eq!(errors("int f(int a, { if (a) { } } int g; int h(void) { return; }"), ["expected one of `...` or type, found `{`"]);
+    assert_eq!(errors("} int x;"), ["expected declaration, found `}`"]);
+    assert_eq!(errors("int x int y;"), ["expected one of `[`, `(`, `=`, `,` or `;`, found keyword `int`"]);
+}
+
+#[test]
+fn syntax_tree_of_a_function() {
+    let source = "int main(int argc, char **argv) {\n    return f(argc, argv[0]);\n}\nint x;\n";
+    let (tree, errors) = cst::parse(Lexer::new(source));
+    assert!(errors.is_empty(), "{:?}", errors);
+    assert_eq!(tree.to_string(), source);
+    let kinds: Vec<_> = tree.nodes().map(|node| node.kind()).collect();
+    assert_eq!(kinds, [cst::SyntaxKind::FunctionDef, cst::SyntaxKind::Declaration]);
+}
+
+#[test]
+fn unchanged_functions_are_reused() {
+    let source: String = (0..20).map(|i| format!("int f{}(int a) {{ return a + {}; }}\n", i, i)).collect();
+    let mut doc = Document::new(source.as_str());
+    let at = source.find("a + 10").unwrap();
+    doc.edit(TextEdit::new(at..at + 1, "b"));
+    assert!(doc.reused() >= 18, "reused {} functions", doc.reused());
+    assert_eq!(doc.errors(), []);
+}
@@ -0,0 +1,169 @@
+use lexer_and_parser::{Document, Lexer, Parser, TextEdit};
+
+// The document must look exactly as if its text was lexed and parsed anew
+fn assert_fresh(doc: &Document) {
+    let tokens = Lexer::new(doc.source()).tokenize();
+    assert_eq!(doc.tokens(), tokens.as_slice(), "tokens of {:?}", doc.source());
+    let (program, errors) = Parser::new(tokens).parse_program();
+    assert_eq!(doc.program(), &program, "program of {:?}", doc.source());
+    assert_eq!(doc.errors(), errors.as_slice(), "errors of {:?}", doc.source());
+}
+
+fn edit(doc: &mut Document, range: std::ops::Range<usize>, text: &str) {
+    doc.edit(TextEdit::new(range, text));
+    assert_fresh(doc);
+}
+
+fn test_files() -> Vec<String> {
+    let mut files: Vec<_> = std::fs::read_dir("test_code")
+        .unwrap()
+        .map(|entry| std::fs::read_to_string(entry.unwrap().path()).unwrap())
+        .collect();
+    files.sort();
+    files
+}
+
+const PROGRAM: &str = "{
+    i = 1;
+    while (i < 10 && a[i] != 0) {
+        if (i > 5) x = x * 2; else x = x + 1;
+        i = i + 1;
+    }
+    /* done */
+    do y = y - 1; while (y >= 0)
+    z = (x + y) / 2;
+}
+";
+
+#[test]
+fn typing_a_program_character_by_character() {
+    let mut doc = Document::new("");
+    for (i, c) in PROGRAM.char_indices() {
+        edit(&mut doc, i..i, &c.to_string());
+    }
+    assert_eq!(doc.source(), PROGRAM);
+}
+
+#[test]
+fn deleting_a_program_from_the_front_and_the_back() {
+    let mut doc = Document::new(PROGRAM);
+    while !doc.source().is_empty() {
+        let len = doc.source().len();
+        edit(&mut doc, len - 1..len, "");
+        if !doc.source().is_empty() {
+            edit(&mut doc, 0..1, "");
+        }
+    }
+}
+
+#[test]
+fn opening_and_closing_comments_and_strings() {
+    let mut doc = Document::new(PROGRAM);
+    let at = PROGRAM.find("i = i").unwrap();
+    edit(&mut doc, at..at, "/*");
+    let end = doc.source().len();
+    edit(&mut doc, end..end, "*/");
+    edit(&mut doc, at..at + 2, "");
+    edit(&mut doc, at..at, "\"");
+    edit(&mut doc, at..at + 1, "'");
+    edit(&mut doc, at..at + 1, "//");
+    edit(&mut doc, at..at + 2, "");
+}
+
+#[test]
+fn edits_that_merge_and_split_tokens() {
+    let mut doc = Document::new("{ a = b + 1; c = d; }");
+    edit(&mut doc, 7..10, ""); // b1
+    edit(&mut doc, 7..7, "+"); // b+1
+    edit(&mut doc, 9..9, "e"); // b+1e
+    edit(&mut doc, 10..10, "+"); // b+1e+
+    edit(&mut doc, 11..11, "5"); // b+1e+5
+    edit(&mut doc, 1..1, "<"); // {<
+    edit(&mut doc, 2..2, "<"); // {<<
+    edit(&mut doc, 3..3, "="); // {<<=
+    let len = doc.source().len();
+    edit(&mut doc, 0..len, "");
+}
+
+#[test]
+fn edits_that_splice_lines() {
+    let mut doc = Document::new("{ a = b..\\ }");
+    edit(&mut doc, 10..10, "\n."); // b..\<newline>. is b...
+    edit(&mut doc, 10..11, ""); // b..\.
+    edit(&mut doc, 2..2, "\\\n"); // a spliced onto the {
+    edit(&mut doc, 2..3, ""); // and the { onto a
+}
+
+#[test]
+fn unchanged_statements_are_reused() {
+    let body: String = (0..50).map(|i| format!("    x{} = {} + y;\n", i, i)).collect();
+    let source = format!("{{\n{}}}\n", body);
+    let mut doc = Document::new(source.as_str());
+
+    let at = source.find("x25").unwrap();
+    edit(&mut doc, at..at + 3, "total");
+    assert!(doc.relexed() <= 3, "relexed {} tokens", doc.relexed());
+    assert!(doc.reused() >= 48, "reused {} statements", doc.reused());
+
+    // Breaking a statement only re-parses up to where statements line up again
+    let at = doc.source().find("x10 =").unwrap();
+    edit(&mut doc, at + 4..at + 5, "");
+    assert!(doc.reused() >= 47, "reused {} statements", doc.reused());
+}
+
+// The error for `)` lists `else` as expected only after an `if`
+#[test]
+fn reuse_depends_on_the_tokens_the_parser_expected() {
+    let mut doc = Document::new("{ if (a) x = 1; ) y = 2; }");
+    edit(&mut doc, 2..9, "");
+    edit(&mut doc, 2..2, "if (a) ");
+}
+
+// `T * x;` declares `x` only while `T` names a type
+#[test]
+fn reuse_depends_on_the_typedef_names_in_scope() {
+    let mut doc = Document::new("{ typedef int T; T * x; x = 0; }");
+    edit(&mut doc, 2..10, ""); // int T;
+    edit(&mut doc, 2..2, "typedef ");
+    edit(&mut doc, 14..15, "U"); // typedef int U;
+    edit(&mut doc, 14..15, "T");
+}
+
+// A fixed pseudo-random sequence of edits over the test files
+#[test]
+fn random_edits_match_parsing_from_scratch() {
+    const SNIPPETS: &[&str] = &[
+        "", " ", "\n", "{", "}", "(", ")", ";", "=", "<", "<=", "+", "-", "*", "/", "/*", "*/", "//", "\"",
+        "'", "if", "else", "while", "do", "break", "x", "12", "0x", "1e", ".", "3.5", "a = 1;", "\\", "\\\n",
+        "<:", ":>", "%:", "&&", "||", "==", "!=", "!", "[", "]", "a[i]",
+        "?", ":", ",", "++", "->", "~", "%", "(int)", "sizeof", "+=",
+        "int ", "typedef ", "T", "struct s { int a; }", "*p", "= { 1, }",
+        "int f(int a) ", "return", "f(x, 1)", "*p = 2;",
+        "for (;;)", "for (int i = 0; ", "switch (x)", "case 1:", "default:", "goto l;", "l:", "continue;",
+    ];
+
+    let mut seed: u64 = 0x2545_f491_4f6c_dd1d;
+    let mut random = |bound: usize| {
+        seed ^= seed << 13;
+        seed ^= seed >> 7;
+        seed ^= seed << 17;
+        (seed % bound as u64) as usize
+    };
+
+    for file in test_files().into_iter().chain([PROGRAM.to_string()]) {
+        let mut doc = Document::new(file.as_str());
+        for _ in 0..300 {
+            let len = doc.source().len();
+            let mut start = random(len + 1);
+            while !doc.source().is_char_boundary(start) {
+                start -= 1;
+            }
+            let mut end = (start + random(8)).min(len);
+            while !doc.source().is_char_boundary(end) {
+                end += 1;
+            }
+            let text = SNIPPETS[random(SNIPPETS.len())];
+            edit(&mut doc, start..end, text);
+        }
+    }
+}
@@ -0,0 +1,50 @@
+use lexer_and_parser::{Lexer, Position, Punct, Span, Standard, Token};
+
+fn span(start: (usize, usize, usize), end: (usize, usize, usize)) -> Span {
+    let position = |(offset, row, column)| Position { offset, row, column };
+    Span::new(position(start), position(end))
+}
+
+fn lex(source: &str) -> Vec<(Token, Span)> {
+    Lexer::new(source).with_standard(Standard::C17).map(|t| (t.token.into_owned(), t.span)).collect()
+}
+
+#[test]
+fn an_unterminated_string_ends_at_the_newline() {
+    let tokens = lex("\"Hello World;\n x = 1;");
+    assert_eq!(
+        tokens[0],
+        (Token::Error("Unterminated string literal".to_string()), span((0, 1, 1), (13, 1, 14)))
+    );
+    assert_eq!(tokens[1], (Token::Identifier("x".to_string()), span((15, 2, 2), (16, 2, 3))));
+    assert_eq!(tokens.len(), 5);
+}
+
+// A malformed pp-number is one error token, and lexing goes on right after it
+#[test]
+fn a_malformed_number_is_one_error_token() {
+    let tokens = lex("123abc;");
+    assert_eq!(
+        tokens,
+        [
+            (Token::Error("invalid suffix 'abc' on integer constant".to_string()), span((0, 1, 1), (6, 1, 7))),
+            (Token::Symbol(Punct::Semi), span((6, 1, 7), (7, 1, 8))),
+        ]
+    );
+    let tokens = lex("1.2.3+x");
+    assert_eq!(
+        tokens[..2],
+        [
+            (Token::Error("too many decimal points in number".to_string()), span((0, 1, 1), (5, 1, 6))),
+            (Token::Symbol(Punct::Plus), span((5, 1, 6), (6, 1, 7))),
+        ]
+    );
+    let tokens = lex("0b101 y");
+    assert_eq!(
+        tokens,
+        [
+            (Token::Error("binary constants require C23".to_string()), span((0, 1, 1), (5, 1, 6))),
+            (Token::Identifier("y".to_string()), span((6, 1, 7), (7, 1, 8))),
+        ]
+    );
+}
@@ -0,0 +1,132 @@
+use lexer_and_parser::number::{evaluate, Constant, FloatType, IntType, NumberError};
+use lexer_and_parser::{Lexer, NumberBase, NumberKind, Standard, Token};
+
+// The value of the one number token in `source`, lexed as C23
+fn value(source: &str) -> Result<Constant, NumberError> {
+    let tokens: Vec<_> = Lexer::new(source).with_standard(Standard::C23).collect();
+    match tokens.as_slice() {
+        [token] => token.token.number_value().unwrap_or_else(|| panic!("not a number: {:?}", token.token)),
+        tokens => panic!("not one token: {:?}", tokens),
+    }
+}
+
+fn int_type(source: &str) -> IntType {
+    match value(source) {
+        Ok(Constant::Integer { ty, .. }) => ty,
+        constant => panic!("not an integer: {:?}", constant),
+    }
+}
+
+#[test]
+fn decimal_constants_stay_signed() {
+    assert_eq!(int_type("12"), IntType::Int);
+    assert_eq!(int_type("2147483647"), IntType::Int);
+    assert_eq!(int_type("2147483648"), IntType::Long);
+    assert_eq!(int_type("9223372036854775807"), IntType::Long);
+    assert_eq!(value("9223372036854775808"), Err(NumberError::IntegerOverflow));
+    assert_eq!(int_type("2147483648ll"), IntType::LongLong);
+}
+
+#[test]
+fn octal_and_hexadecimal_constants_may_be_unsigned() {
+    assert_eq!(int_type("0x7FFFFFFF"), IntType::Int);
+    assert_eq!(int_type("0xFFFFFFFF"), IntType::UnsignedInt);
+    assert_eq!(int_type("037777777777"), IntType::UnsignedInt);
+    assert_eq!(int_type("0x100000000"), IntType::Long);
+    assert_eq!(int_type("0xFFFFFFFFFFFFFFFF"), IntType::UnsignedLong);
+    assert_eq!(int_type("0b11"), IntType::Int);
+    assert_eq!(value("0x1FFFFFFFFFFFFFFFF"), Err(NumberError::IntegerOverflow));
+}
+
+#[test]
+fn suffixes_narrow_the_candidates() {
+    assert_eq!(int_type("1u"), IntType::UnsignedInt);
+    assert_eq!(int_type("4294967296u"), IntType::UnsignedLong);
+    assert_eq!(int_type("1l"), IntType::Long);
+    assert_eq!(int_type("0xFFFFFFFFFFFFFFFFl"), IntType::UnsignedLong);
+    assert_eq!(int_type("1ul"), IntType::UnsignedLong);
+    assert_eq!(int_type("1LU"), IntType::UnsignedLong);
+    assert_eq!(int_type("1ll"), IntType::LongLong);
+    assert_eq!(int_type("0x8000000000000000LL"), IntType::UnsignedLongLong);
+    assert_eq!(int_type("1ull"), IntType::UnsignedLongLong);
+    assert_eq!(value("18446744073709551615u"), Ok(Constant::Integer { value: u64::MAX, ty: IntType::UnsignedLong }));
+    assert_eq!(value("18446744073709551616"), Err(NumberError::IntegerOverflow));
+    assert_eq!(value("18446744073709551616ull"), Err(NumberError::IntegerOverflow));
+}
+
+// The narrowest `_BitInt` that holds the value, with a sign bit unless `u`
+#[test]
+fn bit_precise_widths() {
+    assert_eq!(int_type("0wb"), IntType::BitInt(2));
+    assert_eq!(int_type("1wb"), IntType::BitInt(2));
+    assert_eq!(int_type("3wb"), IntType::BitInt(3));
+    assert_eq!(int_type("255wb"), IntType::BitInt(9));
+    assert_eq!(int_type("0uwb"), IntType::UnsignedBitInt(1));
+    assert_eq!(int_type("255uwb"), IntType::UnsignedBitInt(8));
+    assert_eq!(int_type("0xFFFFFFFFFFFFFFFFuwb"), IntType::UnsignedBitInt(64));
+    assert_eq!(IntType::BitInt(9).max_value(), 255);
+}
+
+#[test]
+fn floating_constants() {
+    assert_eq!(value("1.5"), Ok(Constant::Floating { value: 1.5, ty: FloatType::Double }));
+    assert_eq!(value("1.5f"), Ok(Constant::Floating { value: 1.5, ty: FloatType::Float }));
+    assert_eq!(value("1e3L"), Ok(Constant::Floating { value: 1000.0, ty: FloatType::LongDouble }));
+    assert_eq!(value("0x1.8p1"), Ok(Constant::Floating { value: 3.0, ty: FloatType::Double }));
+    assert_eq!(value("0x.8p-1f"), Ok(Constant::Floating { value: 0.25, ty: FloatType::Float }));
+    assert_eq!(value("1e39f"), Err(NumberError::FloatOverflow { ty: FloatType::Float }));
+    assert_eq!(value("1e39"), Ok(Constant::Floating { value: 1e39, ty: FloatType::Double }));
+    assert_eq!(value("1e309"), Err(NumberError::FloatOverflow { ty: FloatType::Double }));
+}
+
+#[test]
+fn malformed_constants() {
+    assert_eq!(value("089"), Err(NumberError::InvalidDigit { digit: '8', base: NumberBase::Octal }));
+
+    // The lexer already turns these into error tokens; evaluating the
+    // literal on its own gives the same errors
+    let integer = |literal, base| evaluate(literal, NumberKind::Integer, base, None);
+    let float = |literal, base| evaluate(literal, NumberKind::FloatingPoint, base, None);
+    assert_eq!(integer("0x", NumberBase::Hexadecimal), Err(NumberError::NoDigits { base: NumberBase::Hexadecimal }));
+    assert_eq!(integer("0b", NumberBase::Binary), Err(NumberError::NoDigits { base: NumberBase::Binary }));
+    assert_eq!(float("1e", NumberBase::Decimal), Err(NumberError::MissingExponent));
+    assert_eq!(float("1e+", NumberBase::Decimal), Err(NumberError::MissingExponent));
+    assert_eq!(float("0x1p", NumberBase::Hexadecimal), Err(NumberError::MissingExponent));
+    assert_eq!(float("0x1.8", NumberBase::Hexadecimal), Err(NumberError::MissingBinaryExponent));
+    assert_eq!(float("0x.p1", NumberBase::Hexadecimal), Err(NumberError::NoDigits { base: NumberBase::Hexadecimal }));
+    assert_eq!(float("0x1.gp1", NumberBase::Hexadecimal), Err(NumberError::InvalidDigit { digit: 'g', base: NumberBase::Hexadecimal }));
+    // Only a dangling exponent is a missing one
+    assert_eq!(float("1.2.3", NumberBase::Decimal), Err(NumberError::MalformedFloat));
+    assert_eq!(float("1e2e3", NumberBase::Decimal), Err(NumberError::MalformedFloat));
+
+    for source in ["0x", "1e", "0x1.8"] {
+        let token = Lexer::new(source).with_standard(Standard::C23).next().unwrap().token;
+        assert!(matches!(token, Token::Error(_)), "{:?} lexed as {:?}", source, token);
+    }
+}
+
+#[test]
+fn separators_and_splices_carry_no_value() {
+    assert_eq!(value("1'000'000"), Ok(Constant::Integer { value: 1_000_000, ty: IntType::Int }));
+    assert_eq!(value("0x7F'FF"), Ok(Constant::Integer { value: 0x7FFF, ty: IntType::Int }));
+    assert_eq!(value("0x1\\\n0"), Ok(Constant::Integer { value: 16, ty: IntType::Int }));
+}
+
+// The text of each token in `source`, lexed as `standard`
+fn lex(source: &str, standard: Standard) -> Vec<String> {
+    Lexer::new(source).with_standard(standard).map(|t| t.token.to_string()).collect()
+}
+
+#[test]
+fn newer_forms_need_a_newer_standard() {
+    assert_eq!(lex("0b101", Standard::C17), ["binary constants require C23"]);
+    assert_eq!(lex("0b101", Standard::C23), ["0b101"]);
+    // Before C23 the quote starts a character constant
+    assert_eq!(lex("1'000", Standard::C17), ["1", "Unclosed character literal"]);
+    assert_eq!(lex("12wb", Standard::C17), ["invalid suffix 'wb' on integer constant"]);
+    assert_eq!(lex("12wb", Standard::C23), ["12wb"]);
+    // Hexadecimal floats came with C99
+    assert_eq!(lex("0x1.8p1", Standard::C89), ["hexadecimal floating constants require C99"]);
+    assert_eq!(lex("0x1p3", Standard::C89), ["hexadecimal floating constants require C99"]);
+    assert_eq!(lex("0x1.8p1", Standard::C99), ["0x1.8p1"]);
+}
@@ -0,0 +1,220 @@
+use lexer_and_parser::{ExpandedToken, Parser, PreprocessError, Preprocessor};
+
+fn spell(tokens: &[ExpandedToken]) -> String {
+    tokens.iter().map(|t| t.token.to_string()).collect::<Vec<_>>().join(" ")
+}
+
+fn preprocess(source: &str) -> (String, Vec<PreprocessError>) {
+    let (tokens, errors) = Preprocessor::new().preprocess("main.c", source);
+    (spell(&tokens), errors)
+}
+
+fn expand(source: &str) -> String {
+    let (text, errors) = preprocess(source);
+    assert!(errors.is_empty(), "{:?}", errors);
+    text
+}
+
+fn messages(source: &str) -> Vec<String> {
+    preprocess(source).1.into_iter().map(|e| e.message).collect()
+}
+
+// The examples of C17 6.10.3.5
+#[test]
+fn standard_examples() {
+    let defines = "
+#define x 3
+#define f(a) f(x * (a))
+#undef x
+#define x 2
+#define g f
+#define z z[0]
+#define h g(~
+#define m(a) a(w)
+#define w 0,1
+#define t(a) a
+#define p() int
+#define q(x) x
+#define r(x,y) x ## y
+#define str(s) # s
+";
+    assert_eq!(
+        expand(&format!("{}f(y+1) + f(f(z)) % t(t(g)(0) + t)(1);", defines)),
+        "f ( 2 * ( y + 1 ) ) + f ( 2 * ( f ( 2 * ( z [ 0 ] ) ) ) ) % f ( 2 * ( 0 ) ) + t ( 1 ) ;"
+    );
+    assert_eq!(
+        expand(&format!("{}g(x+(3,4)-w) | h 5) & m\n(f)^m(m);", defines)),
+        "f ( 2 * ( 2 + ( 3 , 4 ) - 0 , 1 ) ) | f ( 2 * ( ~ 5 ) ) & f ( 2 * ( 0 , 1 ) ) ^ m ( 0 , 1 ) ;"
+    );
+    assert_eq!(
+        expand(&format!("{}p() i[q()] = {{ q(1), r(2,3), r(4,), r(,5), r(,) }};", defines)),
+        "int i [ ] = { 1 , 23 , 4 , 5 , } ;"
+    );
+    assert_eq!(
+        expand(&format!("{}char c[2][6] = {{ str(hello), str() }};", defines)),
+        "char c [ 2 ] [ 6 ] = { \"hello\" , \"\" } ;"
+    );
+}
+
+#[test]
+fn stringification_and_pasting() {
+    let defines = "
+#define str(s) # s
+#define xstr(s) str(s)
+#define glue(a, b) a ## b
+#define xglue(a, b) glue(a, b)
+#define HIGHLOW \"hello\"
+#define LOW LOW \", world\"
+#define INCFILE(n) vers ## n
+";
+    assert_eq!(expand(&format!("{}glue(HIGH, LOW);", defines)), "\"hello\" ;");
+    assert_eq!(expand(&format!("{}xglue(HIGH, LOW)", defines)), "\"hello\" \", world\"");
+    assert_eq!(expand(&format!("{}xstr(INCFILE(2).h)", defines)), "\"vers2.h\"");
+    assert_eq!(expand(&format!("{}str( a  +\n b  \"\\n\" )", defines)), "\"a + b \\\"\\\\n\\\"\"");
+    assert_eq!(
+        messages("#define cat(a, b) a ## b\ncat(+, /)"),
+        ["pasting \"+\" and \"/\" does not give a valid preprocessing token"]
+    );
+}
+
+#[test]
+fn variadic_macros() {
+    let defines = "#define debug(...) fprintf(stderr, __VA_ARGS__)\n#define report(test, ...) ((test) ? puts(#test) : printf(__VA_ARGS__))\n";
+    assert_eq!(
+        expand(&format!("{}debug(\"X = %d\", x);", defines)),
+        "fprintf ( stderr , \"X = %d\" , x ) ;"
+    );
+    assert_eq!(
+        expand(&format!("{}report(x>y, \"x is %d but y is %d\", x, y);", defines)),
+        "( ( x > y ) ? puts ( \"x>y\" ) : printf ( \"x is %d but y is %d\" , x , y ) ) ;"
+    );
+}
+
+#[test]
+fn conditional_compilation() {
+    let source = "
+#define A 2
+#if defined(A) && A * 2 == 4 && !defined B
+one
+#elif 1 / 0
+two
+#else
+three
+#endif
+#ifdef B
+#if garbage (
+#endif
+#elif -1 < 0u
+four
+#else
+five
+#endif
+#ifndef A
+six
+#endif
+#if 0 && 1 / 0 || 'a' == 97 && (0x10 >> 2) == 4 ? 1 : 1 / 0
+seven
+#endif
+";
+    assert_eq!(expand(source), "one five seven");
+}
+
+#[test]
+fn includes_and_include_guards() {
+    let mut pp = Preprocessor::new()
+        .with_file("guard.h", "#ifndef GUARD\n#define GUARD\nint x;\n#endif\n")
+        .with_file("nested.h", "#include \"guard.h\"\nNESTED")
+        .with_define("HEADER", "<nested.h>");
+    let (tokens, errors) = pp.preprocess("main.c", "#include \"guard.h\"\n#include HEADER\nint y;");
+    assert!(errors.is_empty(), "{:?}", errors);
+    assert_eq!(spell(&tokens), "int x ; NESTED int y ;");
+    assert_eq!(pp.file(tokens[0].spelling.file).name(), "guard.h");
+    assert_eq!(pp.file(tokens[3].spelling.file).name(), "nested.h");
+    assert_eq!(pp.file(tokens[4].spelling.file).name(), "main.c");
+}
+
+#[test]
+fn includes_from_disk() {
+    let dir = std::env::temp_dir().join(format!("lexer_and_parser_pp_{}", std::process::id()));
+    std::fs::create_dir_all(dir.join("include")).unwrap();
+    std::fs::write(dir.join("main.c"), "#include \"local.h\"\n#include <system.h>\nLOCAL SYSTEM").unwrap();
+    std::fs::write(dir.join("local.h"), "#define LOCAL 1").unwrap();
+    std::fs::write(dir.join("include/system.h"), "#define SYSTEM 2").unwrap();
+
+    let mut pp = Preprocessor::new().with_include_dir(dir.join("include"));
+    let (tokens, errors) = pp.preprocess_file(dir.join("main.c")).unwrap();
+    std::fs::remove_dir_all(&dir).unwrap();
+    assert!(errors.is_empty(), "{:?}", errors);
+    assert_eq!(spell(&tokens), "1 2");
+}
+
+// Running again replaces the main file and the command line rather than
+// adding new ones
+#[test]
+fn runs_reuse_their_files() {
+    let mut pp = Preprocessor::new().with_define("N", "1");
+    let (first, _) = pp.preprocess("a.c", "x N");
+    let (second, errors) = pp.preprocess("b.c", "y N N");
+    assert!(errors.is_empty(), "{:?}", errors);
+    assert_eq!(spell(&second), "y 1 1");
+    assert_eq!(second[0].spelling.file, first[0].spelling.file);
+    assert_eq!(second[1].spelling.file, first[1].spelling.file);
+    let main = pp.file(second[0].spelling.file);
+    assert_eq!((main.name(), main.text()), ("b.c", "y N N"));
+
+    // New defines take effect in the same command line file
+    let mut pp = pp.with_define("M", "2");
+    let (third, _) = pp.preprocess("c.c", "M");
+    assert_eq!(spell(&third), "2");
+    assert_eq!(third[0].spelling.file, first[1].spelling.file);
+}
+
+#[test]
+fn expanded_tokens_keep_both_locations() {
+    let source = "#define ONE 1\n#define ADD(a, b) a + b\nx = ADD(ONE, y);";
+    let mut pp = Preprocessor::new();
+    let (tokens, _) = pp.preprocess("main.c", source);
+    let text = |span: lexer_and_parser::Span| &source[span.range()];
+
+    // `x` is not from a macro, `1` is spelled in the definition of ONE and
+    // `+` in ADD's, `y` in the argument; all come from the ADD invocation
+    assert_eq!(tokens[0].expansion, None);
+    assert_eq!(text(tokens[2].spelling.span), "1");
+    assert_eq!(text(tokens[3].spelling.span), "+");
+    assert_eq!(text(tokens[4].spelling.span), "y");
+    for token in &tokens[2..5] {
+        assert_eq!(text(token.expansion.unwrap().span), "ADD(ONE, y)");
+    }
+    assert_eq!(text(tokens[5].location().span), ";");
+}
+
+#[test]
+fn errors() {
+    assert_eq!(messages("#if 1\n"), ["unterminated conditional directive"]);
+    assert_eq!(messages("#endif"), ["#endif without #if"]);
+    assert_eq!(messages("#if 0\n#else\n#else\n#endif"), ["#else after #else"]);
+    assert_eq!(messages("#include \"missing.h\""), ["'missing.h' file not found"]);
+    assert_eq!(messages("#frobnicate"), ["invalid preprocessing directive #frobnicate"]);
+    assert_eq!(messages("#if 1 / 0\n#endif"), ["division by zero in #if"]);
+    assert_eq!(messages("#define f(a, b) a\nf(1)"), ["macro 'f' requires 2 arguments, but only 1 given"]);
+    assert_eq!(messages("#define f(a) a\nf(1"), ["unterminated argument list invoking macro 'f'"]);
+    assert_eq!(messages("#define f(a) #b"), ["'#' is not followed by a macro parameter"]);
+    assert_eq!(messages("#define A 1\n#define A 2"), ["'A' redefined"]);
+    assert_eq!(messages("#define I \"self.h\"\n#include I"), ["'self.h' file not found"]);
+    assert!(messages("#define A 1\n#define A  1\n#define f(a) a\nf\n").is_empty());
+}
+
+#[test]
+fn preprocessed_tokens_parse() {
+    let source = "#define LIMIT 10\n#define STEP(v) v = v + 1;\n{ while (i < LIMIT) { STEP(i) } }";
+    let (tokens, errors) = Preprocessor::new().preprocess("main.c", source);
+    assert!(errors.is_empty());
+    let (_, errors) = Parser::new(tokens.into_iter().map(ExpandedToken::into_positioned)).parse_program();
+    assert!(errors.is_empty(), "{:?}", errors);
+}
+
+#[test]
+fn directives_after_line_splicing() {
+    assert_eq!(expand("%:define STR(x) %:x\n#define LONG 1 + \\\n  2\nSTR(<:) LONG"), "\"<:\" 1 + 2");
+    assert_eq!(expand("#def\\\nine A 1\nA"), "1");
+}
@@ -0,0 +1,88 @@
+use lexer_and_parser::{Keyword, LanguageProfile, Lexer, LexerConfig, Punct, Token};
+
+const PROFILES: [LanguageProfile; 6] = [
+    LanguageProfile::Teaching,
+    LanguageProfile::C89,
+    LanguageProfile::C99,
+    LanguageProfile::C11,
+    LanguageProfile::C17,
+    LanguageProfile::C23,
+];
+
+fn lex(source: &str, config: &LexerConfig) -> Vec<Token> {
+    Lexer::new_with_config(source, config).map(|t| t.token.into_owned()).collect()
+}
+
+fn lex_as(source: &str, profile: LanguageProfile) -> Vec<Token> {
+    lex(source, &LexerConfig::new(profile))
+}
+
+fn identifier(name: &str) -> Token {
+    Token::Identifier(name.to_string())
+}
+
+#[test]
+fn main_is_an_identifier_in_every_profile() {
+    for profile in PROFILES {
+        let tokens = lex_as("int main()", profile);
+        assert_eq!(tokens[1..], [identifier("main"), Token::Symbol(Punct::LParen), Token::Symbol(Punct::RParen)]);
+        // The teaching grammar has no types
+        let int = if profile == LanguageProfile::Teaching { identifier("int") } else { Token::Keyword(Keyword::Int) };
+        assert_eq!(tokens[0], int, "{:?}", profile);
+    }
+}
+
+#[test]
+fn bool_came_with_c99() {
+    assert_eq!(lex_as("_Bool", LanguageProfile::C89), [identifier("_Bool")]);
+    assert_eq!(lex_as("_Bool", LanguageProfile::C99), [Token::Keyword(Keyword::_Bool)]);
+    assert_eq!(lex_as("_Bool", LanguageProfile::C23), [Token::Keyword(Keyword::_Bool)]);
+}
+
+#[test]
+fn bool_and_true_are_keywords_only_in_c23() {
+    for profile in PROFILES {
+        let expected = if profile == LanguageProfile::C23 {
+            vec![Token::Keyword(Keyword::Bool), Token::Keyword(Keyword::True)]
+        } else {
+            vec![identifier("bool"), identifier("true")]
+        };
+        assert_eq!(lex_as("bool true", profile), expected, "{:?}", profile);
+    }
+}
+
+#[test]
+fn extension_keywords() {
+    let config = LexerConfig::new(LanguageProfile::C17).with_keyword("__asm__").with_keyword("bool");
+    assert_eq!(
+        lex("__asm__ bool true asm", &config),
+        [
+            Token::Keyword(Keyword::Extension("__asm__")),
+            Token::Keyword(Keyword::Bool),
+            identifier("true"),
+            identifier("asm"),
+        ]
+    );
+    // The profile is untouched
+    assert_eq!(lex_as("__asm__", LanguageProfile::C17), [identifier("__asm__")]);
+    // A spelling read at run time is leaked to get its `'static`
+    let spelling = String::from("__restrict");
+    let config = LexerConfig::new(LanguageProfile::C17).with_keyword(spelling.leak());
+    assert_eq!(lex("__restrict", &config), [Token::Keyword(Keyword::Extension("__restrict"))]);
+}
+
+#[test]
+fn digraphs_came_with_c95() {
+    // The teaching profile has no `:` to fall back to
+    for profile in PROFILES[1..].iter().copied() {
+        let expected = match profile {
+            LanguageProfile::C89 => vec![Token::Symbol(Punct::Lt), Token::Symbol(Punct::Colon)],
+            _ => vec![Token::Symbol(Punct::LBracket)],
+        };
+        assert_eq!(lex_as("<:", profile), expected, "{:?}", profile);
+    }
+    let config = LexerConfig::new(LanguageProfile::C89).with_digraphs(true);
+    assert_eq!(lex("<:", &config), [Token::Symbol(Punct::LBracket)]);
+    let config = LexerConfig::new(LanguageProfile::C17).with_digraphs(false);
+    assert_eq!(lex("<%", &config), [Token::Symbol(Punct::Lt), Token::Symbol(Punct::Percent)]);
+}
@@ -0,0 +1,64 @@
+use lexer_and_parser::{Lexer, Token, SYMBOLS};
+
+fn symbols(input: &str) -> Vec<String> {
+    Lexer::new(input)
+        .map(|t| match t.token {
+            Token::Symbol(s) => s.to_string(),
+            other => format!("<{}>", other.raw()),
+        })
+        .collect()
+}
+
+const C17_PUNCTUATORS: &[&str] = &[
+    "[", "]", "(", ")", "{", "}", ".", "->",
+    "++", "--", "&", "*", "+", "-", "~", "!",
+    "/", "%", "<<", ">>", "<", ">", "<=", ">=", "==", "!=", "^", "|", "&&", "||",
+    "?", ":", ";", "...",
+    "=", "*=", "/=", "%=", "+=", "-=", "<<=", ">>=", "&=", "^=", "|=",
+    ",", "#", "##",
+];
+
+#[test]
+fn symbol_table_is_the_c17_punctuator_set() {
+    let mut table: Vec<_> = SYMBOLS.to_vec();
+    let mut expected: Vec<_> = C17_PUNCTUATORS.to_vec();
+    table.sort();
+    expected.sort();
+    assert_eq!(table, expected);
+}
+
+#[test]
+fn every_punctuator_lexes_as_one_symbol() {
+    for punct in C17_PUNCTUATORS {
+        assert_eq!(symbols(punct), vec![punct.to_string()], "lexing {:?}", punct);
+    }
+}
+
+#[test]
+fn every_punctuator_lexes_between_identifiers() {
+    for punct in C17_PUNCTUATORS {
+        let input = format!("a{}b", punct);
+        assert_eq!(symbols(&input), vec!["<a>", punct, "<b>"], "lexing {:?}", input);
+    }
+}
+
+#[test]
+fn maximal_munch() {
+    assert_eq!(symbols("a+++b"), ["<a>", "++", "+", "<b>"]);
+    assert_eq!(symbols("a-----b"), ["<a>", "--", "--", "-", "<b>"]);
+    assert_eq!(symbols("x-->y"), ["<x>", "--", ">", "<y>"]);
+    assert_eq!(symbols("a<<=b"), ["<a>", "<<=", "<b>"]);
+    assert_eq!(symbols("a<<<b"), ["<a>", "<<", "<", "<b>"]);
+    assert_eq!(symbols("a&&&b"), ["<a>", "&&", "&", "<b>"]);
+    assert_eq!(symbols("p->x"), ["<p>", "->", "<x>"]);
+    assert_eq!(symbols("a->-b"), ["<a>", "->", "-", "<b>"]);
+    assert_eq!(symbols("###"), ["##", "#"]);
+    assert_eq!(symbols("a/=b"), ["<a>", "/=", "<b>"]);
+}
+
+#[test]
+fn partial_ellipsis_falls_back_to_dots() {
+    assert_eq!(symbols(".."), [".", "."]);
+    assert_eq!(symbols("...."), ["...", "."]);
+    assert_eq!(symbols("....."), ["...", ".", "."]);
+}
@@ -0,0 +1,121 @@
+use lexer_and_parser::ast::{Block, Item, Stmt};
+use lexer_and_parser::{Lexer, Parser};
+
+// The program as nested statement kinds, with `error` for placeholders
+fn shape(source: &str) -> (String, Vec<(String, usize, usize)>) {
+    let (program, errors) = Parser::new(Lexer::new(source)).parse_program();
+    let items: Vec<_> = program
+        .items
+        .iter()
+        .map(|item| match item {
+            Item::Function(function) => format!("fn{}", block(&function.body)),
+            Item::Decl(_) => "decl".to_string(),
+            Item::Block(b) => block(b),
+            Item::Error { .. } => "error".to_string(),
+        })
+        .collect();
+    let errors = errors.iter().map(|e| (e.to_string(), e.span.start.offset, e.span.end.offset)).collect();
+    (items.join(" "), errors)
+}
+
+fn block(block: &Block) -> String {
+    format!("{{{}}}", block.stmts.iter().map(stmt).collect::<Vec<_>>().join(" "))
+}
+
+fn stmt(stmt: &Stmt) -> String {
+    match stmt {
+        Stmt::Error { .. } => "error".to_string(),
+        Stmt::Expr { .. } => "expr".to_string(),
+        Stmt::Decl(_) => "decl".to_string(),
+        Stmt::Break { .. } => "break".to_string(),
+        Stmt::Block(b) => block(b),
+        Stmt::If { then_branch, else_branch: Some(else_branch), .. } => {
+            format!("if({} else {})", self::stmt(then_branch), self::stmt(else_branch))
+        }
+        Stmt::If { then_branch, .. } => format!("if({})", self::stmt(then_branch)),
+        Stmt::While { body, .. } => format!("while({})", self::stmt(body)),
+        Stmt::DoWhile { body, .. } => format!("do({})", self::stmt(body)),
+        Stmt::Switch { body, .. } => format!("switch{}", self::stmt(body)),
+        Stmt::Case { body, .. } => format!("case({})", self::stmt(body)),
+        Stmt::Default { body, .. } => format!("default({})", self::stmt(body)),
+        stmt => panic!("unexpected statement: {:?}", stmt),
+    }
+}
+
+fn error(message: &str, start: usize, end: usize) -> (String, usize, usize) {
+    (message.to_string(), start, end)
+}
+
+#[test]
+fn every_independent_error_is_reported() {
+    let source = "{ x = ; y = 1; z = ) ; w = 2; v = 3 }";
+    let (shape, errors) = shape(source);
+    assert_eq!(shape, "{error expr error expr error}");
+    assert_eq!(
+        errors,
+        [
+            error("expected expression, found `;`", 6, 7),
+            error("expected expression, found `)`", 19, 20),
+            error("expected `;`, found `}`", 36, 37),
+        ]
+    );
+}
+
+#[test]
+fn recovery_resumes_after_a_semicolon() {
+    assert_eq!(shape("{ x = 1 2 3; y = 2; }").0, "{error expr}");
+}
+
+// The `}` belongs to the block around the broken statement
+#[test]
+fn recovery_resumes_at_a_closing_brace() {
+    let (shape, errors) = shape("{ { x = ] } y = 1; }");
+    assert_eq!(shape, "{{error} expr}");
+    assert_eq!(errors, [error("expected expression, found `]`", 8, 9)]);
+}
+
+#[test]
+fn recovery_resumes_at_else_and_while() {
+    let (shape, errors) = shape("{ if (a) x = + else y = 1; do x = * while (a) z = 1; }");
+    assert_eq!(shape, "{if(error else expr) do(error) expr}");
+    assert_eq!(
+        errors,
+        [
+            error("expected expression, found keyword `else`", 15, 19),
+            error("expected expression, found keyword `while`", 36, 41),
+        ]
+    );
+}
+
+#[test]
+fn recovery_resumes_at_the_next_case() {
+    let source = "{ switch (c) { case 1: x = ] case 2: y = 1; default: z = ] default: w = 1; } }";
+    let (shape, errors) = shape(source);
+    assert_eq!(shape, "{switch{case(error) case(expr) default(error) default(expr)}}");
+    assert_eq!(
+        errors,
+        [error("expected expression, found `]`", 27, 28), error("expected expression, found `]`", 57, 58)]
+    );
+}
+
+// A broken condition keeps the statement around it
+#[test]
+fn conditions_recover_at_their_closing_parenthesis() {
+    let (shape, errors) = shape("{ while (a +) x = 1; if (]) y = 2; }");
+    assert_eq!(shape, "{while(expr) if(expr)}");
+    assert_eq!(errors.len(), 2);
+}
+
+#[test]
+fn broken_items_become_error_items() {
+    let (shape, errors) = shape("int f( ; int g; int h(void) { x = ; } } int i;");
+    assert_eq!(shape, "error decl fn{error} error decl");
+    assert_eq!(
+        errors,
+        [
+            error("expected one of `)` or type, found `;`", 7, 8),
+            error("expected expression, found `;`", 34, 35),
+            error("expected declaration, found `}`", 38, 39),
+        ]
+    );
+}
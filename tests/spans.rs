@@ -0,0 +1,75 @@
+use lexer_and_parser::{Lexer, Position, Span, Token};
+
+fn span(start: (usize, usize, usize), end: (usize, usize, usize)) -> Span {
+    let position = |(offset, row, column)| Position { offset, row, column };
+    Span::new(position(start), position(end))
+}
+
+fn lex(source: &str) -> Vec<(Token, Span)> {
+    Lexer::new(source).tokenize().into_iter().map(|t| (t.token, t.span)).collect()
+}
+
+fn identifier(name: &str) -> Token {
+    Token::Identifier(name.to_string())
+}
+
+// Offsets count bytes and columns count chars
+#[test]
+fn columns_count_characters_after_multi_byte_text() {
+    let tokens = lex("\"é\" x");
+    assert_eq!(tokens[0].1, span((0, 1, 1), (4, 1, 4)));
+    assert_eq!(tokens[1], (identifier("x"), span((5, 1, 5), (6, 1, 6))));
+
+    let tokens = lex("/* ü */ y");
+    assert_eq!(tokens[0], (Token::Comment("ü".to_string()), span((0, 1, 1), (8, 1, 8))));
+    assert_eq!(tokens[1], (identifier("y"), span((9, 1, 9), (10, 1, 10))));
+
+    let tokens = lex("日本 = 1;");
+    assert_eq!(tokens[0], (identifier("日本"), span((0, 1, 1), (6, 1, 3))));
+    assert_eq!(tokens[1].1, span((7, 1, 4), (8, 1, 5)));
+}
+
+#[test]
+fn crlf_ends_a_line() {
+    assert_eq!(
+        lex("a\r\n  b"),
+        [(identifier("a"), span((0, 1, 1), (1, 1, 2))), (identifier("b"), span((5, 2, 3), (6, 2, 4)))]
+    );
+    // The `\r` does not belong to a line comment
+    assert_eq!(
+        lex("// c\r\nz"),
+        [
+            (Token::Comment("c".to_string()), span((0, 1, 1), (4, 1, 5))),
+            (identifier("z"), span((6, 2, 1), (7, 2, 2))),
+        ]
+    );
+    // Nor to an unterminated string or character constant
+    assert_eq!(
+        lex("\"ab\r\nz"),
+        [
+            (Token::Error("Unterminated string literal".to_string()), span((0, 1, 1), (3, 1, 4))),
+            (identifier("z"), span((5, 2, 1), (6, 2, 2))),
+        ]
+    );
+    assert_eq!(
+        lex("'a\r\nz"),
+        [
+            (Token::Error("Unclosed character literal".to_string()), span((0, 1, 1), (2, 1, 3))),
+            (identifier("z"), span((4, 2, 1), (5, 2, 2))),
+        ]
+    );
+}
+
+#[test]
+fn tokens_after_comments() {
+    let tokens = lex("/* a\nbc */ w // d\nx");
+    assert_eq!(
+        tokens,
+        [
+            (Token::Comment("a\nbc".to_string()), span((0, 1, 1), (10, 2, 6))),
+            (identifier("w"), span((11, 2, 7), (12, 2, 8))),
+            (Token::Comment("d".to_string()), span((13, 2, 9), (17, 2, 13))),
+            (identifier("x"), span((18, 3, 1), (19, 3, 2))),
+        ]
+    );
+}
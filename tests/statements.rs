@@ -0,0 +1,81 @@
+use lexer_and_parser::ast::{Expr, ForInit, Item, Stmt};
+use lexer_and_parser::{Lexer, Parser};
+
+// The statements of the block `source`
+fn parse(source: &str) -> Vec<Stmt> {
+    let (program, errors) = Parser::new(Lexer::new(source)).parse_program();
+    assert!(errors.is_empty(), "{:?}", errors);
+    let Some(Item::Block(block)) = program.items.into_iter().next() else { panic!("not a block") };
+    block.stmts
+}
+
+fn errors(source: &str) -> Vec<String> {
+    let (_, errors) = Parser::new(Lexer::new(source)).parse_program();
+    errors.iter().map(|e| e.to_string()).collect()
+}
+
+#[test]
+fn for_loops_with_and_without_parts() {
+    let stmts = parse("{ for (int i = 0, j; i < n; i++, j--) ; for (i = 0; ; ) break; for (;;) { continue; } }");
+    let Stmt::For { init: Some(init), cond: Some(_), step: Some(Expr::Comma { .. }), body, .. } = &stmts[0] else {
+        panic!("not a full for loop: {:?}", stmts[0]);
+    };
+    let ForInit::Decl(decl) = init.as_ref() else { panic!("not a declaration") };
+    assert_eq!(decl.declarators.len(), 2);
+    assert!(matches!(body.as_ref(), Stmt::Expr { expr: None, .. }));
+    assert!(matches!(&stmts[1], Stmt::For { init: Some(init), cond: None, step: None, .. }
+        if matches!(init.as_ref(), ForInit::Expr(Expr::Assign { .. }))));
+    assert!(matches!(&stmts[2], Stmt::For { init: None, cond: None, step: None, .. }));
+}
+
+// `T` names a type only inside the loop that declares it
+#[test]
+fn for_declarations_are_scoped_to_the_loop() {
+    let stmts = parse("{ for (typedef int T; ; ) T * x; T * y; }");
+    let Stmt::For { body, .. } = &stmts[0] else { panic!("not a for loop") };
+    assert!(matches!(body.as_ref(), Stmt::Decl(_)));
+    assert!(matches!(&stmts[1], Stmt::Expr { expr: Some(Expr::Binary { .. }), .. }));
+}
+
+#[test]
+fn switch_cases_fall_through() {
+    let stmts = parse("{ switch (c) { case 1: case 2 + 1: x = 1; default: y = 2; break; case 4: ; } }");
+    let Stmt::Switch { body, .. } = &stmts[0] else { panic!("not a switch") };
+    let Stmt::Block(block) = body.as_ref() else { panic!("not a block") };
+    // Labels nest around the first statement after them; the rest follows
+    let Stmt::Case { value: Expr::Num(one), body, .. } = &block.stmts[0] else { panic!("not a case") };
+    assert_eq!(one.literal, "1");
+    assert!(matches!(body.as_ref(), Stmt::Case { value: Expr::Binary { .. }, body, .. } if matches!(body.as_ref(), Stmt::Expr { .. })));
+    assert!(matches!(&block.stmts[1], Stmt::Default { .. }));
+    assert!(matches!(&block.stmts[2], Stmt::Break { .. }));
+    assert!(matches!(&block.stmts[3], Stmt::Case { body, .. } if matches!(body.as_ref(), Stmt::Expr { expr: None, .. })));
+}
+
+#[test]
+fn labels_and_goto() {
+    let stmts = parse("{ typedef int T; again: T: goto again; f(x); i++; }");
+    let Stmt::Labeled { label, body, .. } = &stmts[1] else { panic!("not a label") };
+    assert_eq!(label.name, "again");
+    // A typedef name is a label too, in a namespace of its own
+    assert!(matches!(body.as_ref(), Stmt::Labeled { label, body, .. }
+        if label.name == "T" && matches!(body.as_ref(), Stmt::Goto { label, .. } if label.name == "again")));
+    assert!(matches!(&stmts[2], Stmt::Expr { expr: Some(Expr::Call { .. }), .. }));
+    assert!(matches!(&stmts[3], Stmt::Expr { expr: Some(Expr::Unary { .. }), .. }));
+}
+
+#[test]
+fn the_control_flow_example() {
+    let source = std::fs::read_to_string("test_code/control_flow.c").unwrap();
+    let (program, errors) = Parser::new(Lexer::new(&source)).parse_program();
+    assert!(errors.is_empty(), "{:?}", errors);
+    assert_eq!(program.items.len(), 3);
+}
+
+#[test]
+fn errors_in_statements() {
+    assert_eq!(errors("{ for (i = 0; i < n) x; }"), ["expected `;`, found `)`"]);
+    assert_eq!(errors("{ goto 1; }"), ["expected identifier, found number `1`"]);
+    assert_eq!(errors("{ continue }"), ["expected `;`, found `}`"]);
+    // Recovery resumes at the next case
+    assert_eq!(errors("{ switch (c) { case 1: x = ) case 2: y = 1; } }"), ["expected expression, found `)`"]);
+}
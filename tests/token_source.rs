@@ -0,0 +1,134 @@
+use std::cell::Cell;
+
+use lexer_and_parser::{Lexer, Parser, Position, PositionedToken, Span, Token, TokenSource};
+
+fn span(start: (usize, usize, usize), end: (usize, usize, usize)) -> Span {
+    let position = |(offset, row, column)| Position { offset, row, column };
+    Span::new(position(start), position(end))
+}
+
+// The `Eof` token after all of `source`, with how many tokens came before it
+fn eof(source: &str) -> (Span, usize) {
+    let mut tokens = TokenSource::new(Lexer::new(source));
+    while !tokens.at_end() {
+        tokens.bump();
+    }
+    (tokens.bump().span, tokens.consumed())
+}
+
+#[test]
+fn tokens_are_pulled_as_the_parser_needs_them() {
+    let source = "{ x = 1; while (x) x = x - 1; }";
+    let pulled = Cell::new(0);
+    let mut parser = Parser::new(Lexer::new(source).inspect(|_| pulled.set(pulled.get() + 1)));
+    // Only the current token is read up front
+    assert_eq!(pulled.get(), 1);
+    let (_, errors) = parser.parse_program();
+    assert_eq!(errors, []);
+    assert_eq!(pulled.get(), Lexer::new(source).count());
+}
+
+#[test]
+fn any_token_iterator_can_feed_the_parser() {
+    let source = "{ if (a < b) { x = a; } else y = (b - a) * 2; do z = z / 2; while (z > 1) }";
+    let streamed = Parser::new(Lexer::new(source)).parse_program();
+    let collected = Parser::new(Lexer::new(source).tokenize()).parse_program();
+    assert_eq!(streamed, collected);
+    assert_eq!(streamed.1, []);
+}
+
+#[test]
+fn empty_input_is_an_empty_program() {
+    let mut parser = Parser::new(Lexer::new(""));
+    let (program, errors) = parser.parse_program();
+    assert_eq!(program.items, []);
+    assert_eq!(errors, []);
+    assert_eq!(eof(""), (span((0, 1, 1), (0, 1, 1)), 0));
+}
+
+#[test]
+fn comment_only_input_is_an_empty_program() {
+    let source = "// one\n/* two */\n";
+    let mut parser = Parser::new(Lexer::new(source));
+    let (program, errors) = parser.parse_program();
+    assert_eq!(program.items, []);
+    assert_eq!(errors, []);
+    let comments: Vec<_> = parser.comments().iter().map(|c| c.token.clone()).collect();
+    assert_eq!(comments, [Token::Comment("one"), Token::Comment("two")]);
+}
+
+// Each comment stands in for as many spaces, so positions line up too
+#[test]
+fn comments_between_tokens_do_not_change_the_program() {
+    let commented = "{ x /**/ = 1; /* a */ if (x) // b\n y = 2; }";
+    let plain = "{ x      = 1;         if (x)     \n y = 2; }";
+    let parse = |source| Parser::new(Lexer::new(source)).parse_program();
+    let (program, errors) = parse(commented);
+    assert_eq!(errors, []);
+    assert_eq!(program, parse(plain).0);
+}
+
+#[test]
+fn eof_is_just_past_the_last_token() {
+    assert_eq!(eof("{\n  x = 1;\n"), (span((10, 2, 9), (10, 2, 9)), 5));
+    assert_eq!(eof("x;  "), (span((2, 1, 3), (2, 1, 3)), 2));
+    // A trailing comment is still input
+    assert_eq!(eof("x; // end"), (span((9, 1, 10), (9, 1, 10)), 2));
+
+    let mut tokens = TokenSource::new(Lexer::new("x"));
+    tokens.bump();
+    assert_eq!(tokens.bump().token, Token::Eof);
+    assert_eq!(tokens.bump().token, Token::Eof);
+    assert_eq!(tokens.consumed(), 1);
+}
+
+fn texts(comments: Vec<PositionedToken<&str>>) -> Vec<Token<&str>> {
+    comments.into_iter().map(|c| c.token).collect()
+}
+
+#[test]
+fn comments_can_be_taken_or_dropped() {
+    let source = "/* a */ x /* b */ y // c\n";
+    let mut parser = Parser::new(Lexer::new(source));
+    // Only the comment before the current token is read so far
+    assert_eq!(texts(parser.take_comments()), [Token::Comment("a")]);
+    assert_eq!(parser.comments(), []);
+    parser.parse_program();
+    assert_eq!(texts(parser.take_comments()), [Token::Comment("b"), Token::Comment("c")]);
+
+    let mut tokens = TokenSource::new(Lexer::new(source)).with_comments(false);
+    while !tokens.at_end() {
+        tokens.bump();
+    }
+    assert_eq!(tokens.comments(), []);
+    assert_eq!(tokens.consumed(), 2);
+}
+
+// Yields its tokens with a `None` after each one, like a channel that is
+// momentarily empty
+struct Stutter<I> {
+    tokens: I,
+    paused: bool,
+}
+
+impl<I: Iterator> Iterator for Stutter<I> {
+    type Item = I::Item;
+
+    fn next(&mut self) -> Option<I::Item> {
+        self.paused = !self.paused;
+        if self.paused {
+            self.tokens.next()
+        } else {
+            None
+        }
+    }
+}
+
+#[test]
+fn the_first_none_ends_the_input() {
+    let mut tokens = TokenSource::new(Stutter { tokens: Lexer::new("x y"), paused: false });
+    assert_eq!(tokens.bump().token, Token::Identifier("x"));
+    assert_eq!(tokens.bump().token, Token::Eof);
+    assert_eq!(tokens.bump().token, Token::Eof);
+    assert_eq!(tokens.consumed(), 1);
+}
@@ -0,0 +1,42 @@
+use lexer_and_parser::{Keyword, Lexer, Punct, Standard, Token, KEYWORDS, SYMBOLS};
+
+#[test]
+fn keywords_round_trip_through_their_spelling() {
+    assert_eq!(Keyword::ALL.len(), KEYWORDS.len());
+    for keyword in Keyword::ALL {
+        assert_eq!(Keyword::from_spelling(&keyword.to_string()), Some(*keyword));
+    }
+    assert_eq!(Keyword::from_spelling("If"), None);
+}
+
+#[test]
+fn punctuators_round_trip_through_their_spelling() {
+    assert_eq!(Punct::ALL.len(), SYMBOLS.len());
+    for punct in Punct::ALL {
+        assert_eq!(Punct::from_spelling(&punct.to_string()), Some(*punct));
+    }
+    assert_eq!(Punct::from_spelling("<>"), None);
+}
+
+#[test]
+fn tokens_display_as_their_source_text() {
+    let source = "while (x <= 0x1F) { s = s->next; c = 'a'; t = \"b\\n\"; }";
+    let tokens: Vec<_> = Lexer::new(source).map(|t| t.token.to_string()).collect();
+    assert_eq!(tokens.join(" "), "while ( x <= 0x1F ) { s = s -> next ; c = 'a' ; t = \"b\\n\" ; }");
+}
+
+// Suffixes keep their case and order, though they mean the same
+#[test]
+fn numbers_display_their_suffix_as_written() {
+    let source = "1ULL 1llu 1uLL 2Lu 0x1Fu 3wbU 1.5F 2.0L 1e3f";
+    let tokens: Vec<_> = Lexer::new(source).with_standard(Standard::C23).map(|t| t.token.to_string()).collect();
+    assert_eq!(tokens.join(" "), source);
+
+    // The suffix itself is the meaning, in its canonical spelling
+    let suffix = |source| match Lexer::new(source).next().unwrap().token {
+        Token::Number { suffix, .. } => suffix.unwrap(),
+        token => panic!("not a number: {:?}", token),
+    };
+    assert_eq!(suffix("1ULL"), suffix("1llu"));
+    assert_eq!(suffix("1ULL").to_string(), "ull");
+}
@@ -0,0 +1,105 @@
+use lexer_and_parser::ast::{Expr, Item, Stmt};
+use lexer_and_parser::{cst, LanguageProfile, Lexer, LexerConfig, Parser, Punct, Token, TriviaKind};
+
+// Each token with where it starts and its source text
+fn lex<'a>(input: &'a str, config: &LexerConfig) -> Vec<(Token, (usize, usize), &'a str)> {
+    Lexer::new_with_config(input, config)
+        .map(|t| (t.token.into_owned(), (t.span.start.row, t.span.start.column), t.span.text(input)))
+        .collect()
+}
+
+#[test]
+fn line_splices_join_tokens_across_lines() {
+    let input = "in\\\nt x = 1\\\n2 <\\\r\n<= y;";
+    let tokens = lex(input, &LexerConfig::default());
+    let shapes: Vec<_> = tokens.iter().map(|(_, at, text)| (*at, *text)).collect();
+    assert_eq!(
+        shapes,
+        [((1, 1), "in\\\nt"), ((2, 3), "x"), ((2, 5), "="), ((2, 7), "1\\\n2"), ((3, 3), "<\\\r\n<="), ((4, 4), "y"), ((4, 5), ";")]
+    );
+    assert!(matches!(tokens[0].0, Token::Keyword(k) if k.as_str() == "int"));
+    assert_eq!(tokens[4].0, Token::Symbol(Punct::ShlEq));
+    let value = tokens[3].0.number_value().unwrap().unwrap();
+    assert_eq!(value, lexer_and_parser::number::Constant::Integer { value: 12, ty: lexer_and_parser::number::IntType::Int });
+}
+
+#[test]
+fn line_splices_continue_comments_and_strings() {
+    let input = "// one \\\n still one\n\"a\\\nb\" x";
+    let tokens = lex(input, &LexerConfig::default());
+    assert!(matches!(tokens[0].0, Token::Comment(_)));
+    assert!(matches!(&tokens[1].0, Token::StringLiteral { value, .. } if value == b"ab"));
+    assert_eq!(tokens[2].1, (4, 4));
+}
+
+#[test]
+fn line_splices_between_tokens_are_trivia() {
+    let input = "a \\\n  + b";
+    let tokens: Vec<_> = Lexer::new(input).lossless().collect();
+    let kinds: Vec<_> = tokens[0].trailing.iter().map(|t| t.kind).collect();
+    assert_eq!(kinds, [TriviaKind::Whitespace, TriviaKind::LineSplice, TriviaKind::Whitespace]);
+    assert_eq!(tokens.iter().map(|t| t.to_string()).collect::<String>(), input);
+}
+
+#[test]
+fn trigraphs_only_when_enabled() {
+    let input = "??=x ??( ??) ??< ??> ??! ??' ??- ??/\ny ?? ?";
+    let symbols = |config: &LexerConfig| -> Vec<String> {
+        lex(input, config).into_iter().map(|(token, _, _)| token.to_string()).collect()
+    };
+    assert_eq!(
+        symbols(&LexerConfig::default().with_trigraphs(true)),
+        ["#", "x", "[", "]", "{", "}", "|", "^", "~", "y", "?", "?", "?"]
+    );
+    assert_eq!(symbols(&LexerConfig::default())[..4], ["?", "?", "=", "x"]);
+
+    // Positions count the three characters of a trigraph
+    let tokens = lex(input, &LexerConfig::default().with_trigraphs(true));
+    assert_eq!((tokens[1].1, tokens[1].2), ((1, 4), "x"));
+    assert_eq!((tokens[9].1, tokens[9].2), ((2, 1), "y"));
+}
+
+// A `??/` splice inside a name is removed like a plain one
+#[test]
+fn trigraph_splices_inside_names() {
+    let input = "{ in??/\nt x; x??/\ny = 1; }";
+    let config = LexerConfig::default().with_trigraphs(true);
+    let tokens = lex(input, &config);
+    assert!(matches!(tokens[1].0, Token::Keyword(k) if k.as_str() == "int"));
+
+    let (program, errors) = Parser::new(Lexer::new_with_config(input, &config)).with_trigraphs(true).parse_program();
+    assert_eq!(errors, []);
+    let [Item::Block(block)] = program.items.as_slice() else { panic!("not a block: {:?}", program.items) };
+    let Stmt::Expr { expr: Some(Expr::Assign { target, .. }), .. } = &block.stmts[1] else {
+        panic!("not an assignment: {:?}", block.stmts[1])
+    };
+    assert!(matches!(target.as_ref(), Expr::Ident(ident) if ident.name == "xy"));
+
+    let (tree, errors) = cst::parse(Lexer::new_with_config(input, &config));
+    assert_eq!(errors, []);
+    assert_eq!(tree.to_string(), input);
+}
+
+#[test]
+fn digraphs_are_their_punctuators() {
+    let input = "<: :> <% %> %: %:%: <::";
+    let tokens = lex(input, &LexerConfig::default());
+    let puncts: Vec<_> = tokens.iter().map(|(token, _, _)| token.clone()).collect();
+    let expected = [
+        Punct::LBracket,
+        Punct::RBracket,
+        Punct::LBrace,
+        Punct::RBrace,
+        Punct::Hash,
+        Punct::HashHash,
+        Punct::LBracket,
+        Punct::Colon,
+    ];
+    assert_eq!(puncts, expected.map(Token::Symbol));
+    // The spelling stays in the source text of the token
+    let spellings: Vec<_> = tokens.iter().map(|(_, _, text)| *text).collect();
+    assert_eq!(spellings, ["<:", ":>", "<%", "%>", "%:", "%:%:", "<:", ":"]);
+
+    let c89 = lex("<:", &LexerConfig::new(LanguageProfile::C89));
+    assert_eq!(c89.len(), 2);
+}